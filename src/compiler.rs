@@ -1,60 +1,469 @@
-use crate::{indent, types::Node};
+use crate::{
+    indent, runtime,
+    types::{Node, Span, Type},
+};
 
-// TODO: Finish implementing this function.
-// Ideally this should be done after typechecking is done;
-//      although the C compiler already typechecks for us
+/// Settings that change the generated C without changing what the program means.
+pub struct Options {
+    /// Whether indexing checks the index against the length of the array at runtime.
+    pub bounds_checks: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            bounds_checks: true,
+        }
+    }
+}
+
+/// Compiles a whole program with the given options.
+pub fn compile_program(ast: Node, options: &Options) -> String {
+    let output = compile(ast);
+    if options.bounds_checks {
+        output
+    } else {
+        format!("#define ARLANG_NO_BOUNDS_CHECKS\n{}", output)
+    }
+}
+
+// Expects a program that passed `typeck::check`, the generated C is not checked again.
 pub fn compile(ast: Node) -> String {
     use Node::*;
 
     match &ast {
-        Program { body } => body
-            .iter()
-            .map(|stmt| compile(stmt.to_owned()))
-            .collect::<Vec<_>>()
-            .join("\n"),
-        Function { name, params, body } => {
-            let mut output = String::new();
+        Program { body, .. } => {
+            let mut output = String::from(runtime::PRELUDE);
+            output.push('\n');
 
-            let params = params
-                .iter()
-                .map(|p| compile(p.clone()))
-                .collect::<Vec<_>>()
-                .join(", ");
+            for definition in type_definitions(&ast) {
+                output.push_str(&definition);
+                output.push_str("\n\n");
+            }
 
-            output.extend(format!("void {}({}) {{\n", name, params).chars());
+            // Prototypes, so that functions can be called before they are defined.
+            let prototypes = body
+                .iter()
+                .filter(|stmt| matches!(stmt, Function { .. }))
+                .map(|function| format!("{};\n", compile_signature(function)))
+                .collect::<String>();
+            if !prototypes.is_empty() {
+                output.push_str(&prototypes);
+                output.push('\n');
+            }
 
-            output.extend(
-                indent(
-                    &body
-                        .iter()
-                        .map(|stmt| compile(stmt.to_owned()))
-                        .collect::<Vec<_>>()
-                        .join(";\n"),
-                    4,
-                )
-                .chars(),
+            output.push_str(
+                &body
+                    .iter()
+                    .filter(|stmt| !matches!(stmt, Struct { .. } | Enum { .. }))
+                    .map(compile_stmt)
+                    .collect::<Vec<_>>()
+                    .join("\n\n"),
             );
+            output.push('\n');
+
+            if body
+                .iter()
+                .any(|stmt| matches!(stmt, Function { name, .. } if name == "main"))
+            {
+                output.push_str(&format!(
+                    "\nint main(void) {{\n    {}();\n    return 0;\n}}\n",
+                    function_name("main", &[])
+                ));
+            }
+
+            output
+        }
+        Function { body, .. } => {
+            let mut output = format!("{} {{\n", compile_signature(&ast));
 
+            output.push_str(&compile_block(body));
+
+            output.push('}');
+
+            output
+        }
+        If {
+            condition,
+            then_body,
+            else_body,
+            ..
+        } => {
+            let mut output = format!("if ({}) {{\n", compile(*condition.clone()));
+            output.push_str(&compile_block(then_body));
             output.push('}');
 
+            match else_body.as_deref() {
+                // The `match` expressions in the condition are computed only if it is reached.
+                Some([else_if @ If { condition, .. }]) if !has_match(condition) => {
+                    output.push_str(" else ");
+                    output.push_str(&compile(else_if.clone()));
+                }
+                Some(body) => {
+                    output.push_str(" else {\n");
+                    output.push_str(&compile_block(body));
+                    output.push('}');
+                }
+                None => {}
+            }
+
             output
         }
-        NumericLiteral { val, typ } => format!("({}){}", typ, val),
+        While {
+            condition,
+            body,
+            span,
+        } if has_match(condition) => format!(
+            // The `match` expressions in the condition are computed again on every iteration.
+            "while (1) {{\n{}    if (!{}) {{\n        break;\n    }}\n{}}}{}",
+            indent(&match_values(condition), 4),
+            compile(*condition.clone()),
+            compile_block(body),
+            loop_end(*span, body)
+        ),
+        While {
+            condition,
+            body,
+            span,
+        } => format!(
+            "while ({}) {{\n{}}}{}",
+            compile(*condition.clone()),
+            compile_block(body),
+            loop_end(*span, body)
+        ),
+        For {
+            var,
+            iter,
+            symbol,
+            iter_type,
+            body,
+            span,
+        } => {
+            let var = variable_name(var, *symbol);
+            let Range { start, end, .. } = iter.as_ref() else {
+                let Some(Type::Vec(element)) = iter_type else {
+                    unreachable!("`for` loops only iterate over ranges and `Vec`s")
+                };
+
+                // The length is read on every iteration, elements pushed by the body are visited too.
+                // A nested loop declares its own `arlang_iter` and `arlang_index` in an inner block.
+                // The body gets a block of its own too, the loop variable is outside of it in arlang.
+                return format!(
+                    "{{\n    {vec}* arlang_iter = {};\n    for ({} arlang_index = 0; arlang_index < arlang_iter->len; arlang_index++) {{\n        {} {var} = arlang_iter->items[arlang_index];\n        (void){var};\n        {{\n{}        }}\n    }}\n}}{}",
+                    compile(*iter.clone()),
+                    c_type_name("int"),
+                    c_type_of(element),
+                    indent(&compile_block(body), 8),
+                    loop_end(*span, body),
+                    vec = vec_name(element),
+                );
+            };
+
+            // The end of the range is evaluated only once, like the start. Program names never
+            // start with `arlang_`, and a nested loop declares its own end in an inner scope.
+            format!(
+                "for ({} {var} = {}, arlang_end = {}; {var} < arlang_end; {var}++) {{\n{}}}{}",
+                c_type_name("int"),
+                compile(*start.clone()),
+                compile(*end.clone()),
+                compile_block(body),
+                loop_end(*span, body),
+            )
+        }
+        Return { value, .. } => match value {
+            Some(value) => format!("return {}", compile(*value.clone())),
+            None => "return".into(),
+        },
+        Break {
+            loop_span: Some(loop_span),
+            ..
+        } => format!("goto {}", break_label(*loop_span)),
+        Break { .. } => "break".into(),
+        Continue { .. } => "continue".into(),
+        NumericLiteral { val, typ, .. } => {
+            // Without the `u`, C would warn that a constant this large does not fit in a `long`.
+            let unsigned = if val.parse::<u64>().is_ok_and(|val| val > i64::MAX as u64) {
+                "u"
+            } else {
+                ""
+            };
+
+            format!("({}){}{}", c_type_name(typ), val, unsigned)
+        }
+        StringLiteral { val, .. } => c_string_literal(val),
+        BoolLiteral { val, .. } => val.to_string(),
+        BinaryExpr {
+            operator,
+            right,
+            span,
+            ..
+        } if is_lazy(operator) && has_match(right) => condition_name(*span),
         BinaryExpr {
             left,
             operator,
             right,
-        } => format!(
-            "{} {} {};",
-            compile(*left.clone()),
+            resolved_type,
+            ..
+        } => {
+            let left = compile(*left.clone());
+            let right = compile(*right.clone());
+
+            match operator.as_str() {
+                "=" | "+=" | "-=" | "*=" | "/=" | "%=" => {
+                    format!("{} {} {}", left, operator, right)
+                }
+                // C promotes integers narrower than `int`, the result is converted back so
+                // that it wraps around like the arlang type does.
+                "+" | "-" | "*" | "/" | "%" => format!(
+                    "(({})({} {} {}))",
+                    c_type_of(resolved_type.as_ref().expect("type checked")),
+                    left,
+                    operator,
+                    right
+                ),
+                // Always parenthesize, so that the grouping of the arlang expression is kept.
+                _ => format!("({} {} {})", left, operator, right),
+            }
+        }
+        UnaryExpr {
+            operand,
             operator,
-            compile(*right.clone())
+            resolved_type: Some(typ),
+            ..
+        } if operator == "-" => match operand.as_ref() {
+            // `-9223372036854775808` is the only literal that does not fit before its negation.
+            NumericLiteral { val, .. } if val == "9223372036854775808" => "INT64_MIN".into(),
+            NumericLiteral { val, .. } => format!("(({})-{})", c_type_of(typ), val),
+            operand => format!("(({})-{})", c_type_of(typ), compile(operand.clone())),
+        },
+        UnaryExpr {
+            operand, operator, ..
+        } => format!("({}{})", operator, compile(*operand.clone())),
+        Cast { expr, typ, .. } => format!("(({}){})", c_type(typ), compile(*expr.clone())),
+        Identifier { name, symbol, .. } | Variable { name, symbol, .. } => {
+            variable_name(name, *symbol)
+        }
+        VarDecl {
+            name,
+            mutable,
+            init,
+            symbol,
+            resolved_type,
+            ..
+        } => {
+            let typ = c_type_of(resolved_type.as_ref().expect("type checked"));
+            // After the type, so that it applies to the variable and not to what a pointer points to.
+            let qualifier = if *mutable { "" } else { " const" };
+
+            format!(
+                "{}{} {} = {}",
+                typ,
+                qualifier,
+                variable_name(name, *symbol),
+                compile(*init.clone())
+            )
+        }
+        // Parameters have a symbol, fields do not.
+        TypedIdentifier {
+            name, typ, symbol, ..
+        } => format!("{} {}", c_type(typ), variable_name(name, *symbol)),
+        Struct { name, fields, .. } => format!(
+            "typedef struct {name} {{\n{}}} {name};",
+            indent(
+                &fields
+                    .iter()
+                    .map(|field| format!("{};", compile(field.clone())))
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+                4
+            ),
+            name = c_name(name),
+        ),
+        Enum { name, variants, .. } => {
+            let tags = variants
+                .iter()
+                .map(|variant| match variant {
+                    Variant { name: variant, .. } => tag_name(name, variant),
+                    _ => unreachable!(),
+                })
+                .collect::<Vec<_>>()
+                .join(", ");
+
+            // Variants without fields have no member in the union, C does not allow empty structs.
+            let members = variants
+                .iter()
+                .filter_map(|variant| match variant {
+                    Variant { name, fields, .. } if !fields.is_empty() => Some(format!(
+                        "struct {{\n{}}} {};",
+                        indent(
+                            &fields
+                                .iter()
+                                .enumerate()
+                                .map(|(i, field)| format!("{} _{};", c_type(field), i))
+                                .collect::<Vec<_>>()
+                                .join("\n"),
+                            4
+                        ),
+                        c_name(name)
+                    )),
+                    _ => None,
+                })
+                .collect::<Vec<_>>();
+
+            let name = c_name(name);
+            let mut output = format!("typedef struct {} {{\n    enum {{ {} }} tag;\n", name, tags);
+            if !members.is_empty() {
+                output.push_str(&indent(
+                    &format!("union {{\n{}}};", indent(&members.join("\n"), 4)),
+                    4,
+                ));
+            }
+            output.push_str(&format!("}} {};", name));
+            output
+        }
+        EnumLiteral {
+            name,
+            variant,
+            args,
+            ..
+        } if args.is_empty() => format!("({}){{.tag = {}}}", c_name(name), tag_name(name, variant)),
+        EnumLiteral {
+            name,
+            variant,
+            args,
+            ..
+        } => format!(
+            "({}){{.tag = {}, .{} = {{{}}}}}",
+            c_name(name),
+            tag_name(name, variant),
+            c_name(variant),
+            args.iter()
+                .map(|arg| compile(arg.clone()))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        // Computed into a temporary before the statement, by `match_values`.
+        Match { span, .. } => match_value_name(*span),
+        StructLiteral { name, fields, .. } => format!(
+            "({}){{{}}}",
+            c_name(name),
+            fields
+                .iter()
+                .map(|field| compile(field.clone()))
+                .collect::<Vec<_>>()
+                .join(", ")
         ),
-        Identifier { name } => name.to_owned(),
-        TypedIdentifier { name, typ } => format!("{}: {}", name, compile(*typ.clone())),
-        CallExpr { callee, args } => format!(
+        FieldInit { name, value, .. } => {
+            format!(".{} = {}", c_name(name), compile(*value.clone()))
+        }
+        ArrayLiteral {
+            elements,
+            resolved_type: Some(Type::Vec(element)),
+            ..
+        } => {
+            // C has no empty arrays, and a null pointer is fine when there is nothing to copy.
+            let items = if elements.is_empty() {
+                "NULL".into()
+            } else {
+                format!(
+                    "({}[]){{{}}}",
+                    c_type_of(element),
+                    elements
+                        .iter()
+                        .map(|element| compile(element.clone()))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            };
+
+            format!("{}_from({}, {})", vec_name(element), items, elements.len())
+        }
+        ArrayLiteral {
+            elements,
+            resolved_type: Some(typ),
+            ..
+        } => format!(
+            "({}){{{{{}}}}}",
+            c_type_of(typ),
+            elements
+                .iter()
+                .map(|element| compile(element.clone()))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        MemberExpr {
+            object,
+            property,
+            computed: true,
+            object_type: Some(Type::Vec(element)),
+            ..
+        } => format!(
+            "(*{}_at({}, {}))",
+            vec_name(element),
+            compile(*object.clone()),
+            compile(*property.clone())
+        ),
+        MemberExpr {
+            object,
+            property,
+            computed: true,
+            ..
+        } => format!(
+            "ARLANG_INDEX({}, {})",
+            compile(*object.clone()),
+            compile(*property.clone())
+        ),
+        MemberExpr {
+            object,
+            property,
+            computed: false,
+            ..
+        } => format!(
+            "{}.{}",
+            compile(*object.clone()),
+            compile(*property.clone())
+        ),
+        // Methods are runtime functions that take the object as their first argument.
+        CallExpr { callee, args, .. } if matches!(callee.as_ref(), MemberExpr { .. }) => {
+            let MemberExpr {
+                object,
+                property,
+                object_type: Some(Type::Vec(element)),
+                ..
+            } = callee.as_ref()
+            else {
+                unreachable!("`Vec` is the only type with methods")
+            };
+
+            format!(
+                "{}_{}({})",
+                vec_name(element),
+                match property.as_ref() {
+                    Identifier { name, .. } => name,
+                    _ => unreachable!(),
+                },
+                std::iter::once(object.as_ref())
+                    .chain(args)
+                    .map(|arg| compile(arg.clone()))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        }
+        CallExpr {
+            callee,
+            args,
+            type_args,
+            ..
+        } => format!(
             "{}({})",
-            compile(*callee.clone()),
+            match callee.as_ref() {
+                Identifier { name, .. } if runtime::BUILTINS.contains(&name.as_str()) => {
+                    runtime::builtin_function(name, type_args.first())
+                        .expect("the type checker only accepts printable values")
+                }
+                Identifier { name, .. } => function_name(name, type_args),
+                _ => unreachable!("the parser only produces calls to identifiers and methods"),
+            },
             args.iter()
                 .map(|arg| compile(arg.clone()))
                 .collect::<Vec<_>>()
@@ -63,3 +472,522 @@ pub fn compile(ast: Node) -> String {
         _ => unimplemented!("{:?} is not implemented yet", ast),
     }
 }
+
+fn compile_stmt(stmt: &Node) -> String {
+    match stmt {
+        Node::Function { .. } | Node::While { .. } => compile(stmt.to_owned()),
+        Node::If { condition, .. } => {
+            format!("{}{}", match_values(condition), compile(stmt.to_owned()))
+        }
+        Node::For { iter, .. } => format!("{}{}", match_values(iter), compile(stmt.to_owned())),
+        Node::Match { scrutinee, .. } => {
+            format!("{}{}", match_values(scrutinee), compile_match(stmt, true))
+        }
+        _ => format!("{}{};", match_values(stmt), compile(stmt.to_owned())),
+    }
+}
+
+/// The statements that compute the `match` expressions in the expression `node` into
+/// temporaries, to go right before the statement that contains it, since C has no expression
+/// that contains statements. C leaves the order in which operands are evaluated unspecified
+/// anyway, only `&&` and `||` need care to evaluate their right operand only when needed.
+fn match_values(node: &Node) -> String {
+    match node {
+        Node::Match { scrutinee, .. } => {
+            format!(
+                "{}{}\n",
+                match_values(scrutinee),
+                compile_match(node, false)
+            )
+        }
+        Node::BinaryExpr {
+            left,
+            operator,
+            right,
+            span,
+            ..
+        } if is_lazy(operator) && has_match(right) => {
+            let name = condition_name(*span);
+            format!(
+                "{}bool {name} = {};\nif ({}{name}) {{\n{}}}\n",
+                match_values(left),
+                compile(*left.clone()),
+                if operator == "&&" { "" } else { "!" },
+                indent(
+                    &format!(
+                        "{}{name} = {};",
+                        match_values(right),
+                        compile(*right.clone())
+                    ),
+                    4
+                ),
+            )
+        }
+        _ => node.children().into_iter().map(match_values).collect(),
+    }
+}
+
+/// Whether the expression `node` contains a `match`.
+fn has_match(node: &Node) -> bool {
+    matches!(node, Node::Match { .. }) || node.children().into_iter().any(has_match)
+}
+
+fn is_lazy(operator: &str) -> bool {
+    matches!(operator, "&&" | "||")
+}
+
+/// The temporary that holds the value of the `match` at `span`.
+fn match_value_name(span: Span) -> String {
+    format!("arlang_value_{}_{}", span.line, span.col)
+}
+
+/// The temporary that holds the value of the `&&` or `||` at `span`. By offsets, since the
+/// operation starts where its left operand does, which can be another `&&` or `||`.
+fn condition_name(span: Span) -> String {
+    format!("arlang_condition_{}_{}", span.start, span.end)
+}
+
+// Compiles the statements of a block, indented and without the surrounding braces.
+fn compile_block(body: &[Node]) -> String {
+    indent(
+        &body.iter().map(compile_stmt).collect::<Vec<_>>().join("\n"),
+        4,
+    )
+}
+
+/// Compiles a `match` to a `switch` on the tag of the enum. As a value, the `switch` assigns
+/// the value of the arm to a temporary, declared right before it.
+fn compile_match(node: &Node, statement: bool) -> String {
+    let Node::Match {
+        scrutinee,
+        arms,
+        scrutinee_type: Some(scrutinee_type),
+        resolved_type: Some(resolved_type),
+        span,
+    } = node
+    else {
+        unreachable!("the type checker fills in the types of every `match`")
+    };
+
+    let mut cases = vec![];
+    let mut covered = vec![];
+    let mut wildcard = false;
+    for arm in arms {
+        let Node::MatchArm { pattern, body, .. } = arm else {
+            unreachable!()
+        };
+
+        // Unreachable arms are skipped, C does not allow duplicate cases.
+        let mut lines = vec![];
+        let label = match pattern.as_ref() {
+            Node::Wildcard { .. } if wildcard => continue,
+            Node::Wildcard { .. } => {
+                wildcard = true;
+                "default:".to_string()
+            }
+            Node::VariantPattern {
+                name,
+                variant,
+                bindings,
+                binding_types,
+                ..
+            } => {
+                if wildcard || covered.contains(&variant) {
+                    continue;
+                }
+                covered.push(variant);
+
+                for (i, (binding, typ)) in bindings.iter().zip(binding_types).enumerate() {
+                    if let Node::Identifier { name, symbol, .. } = binding {
+                        if name != "_" {
+                            lines.push(format!(
+                                "{} {name} = arlang_match.{}._{};\n(void){name};",
+                                c_type_of(typ),
+                                c_name(variant),
+                                i,
+                                name = variable_name(name, *symbol)
+                            ));
+                        }
+                    }
+                }
+                format!("case {}:", tag_name(name, variant))
+            }
+            _ => unreachable!(),
+        };
+
+        lines.push(match body.as_ref() {
+            Node::Block { body, .. } if body.is_empty() => String::new(),
+            // In a block of its own, the bindings are outside of it in arlang.
+            Node::Block { body, .. } => format!("{{\n{}}}", compile_block(body)),
+            body if statement => compile_stmt(body),
+            body => format!(
+                "{}{} = {};",
+                match_values(body),
+                match_value_name(*span),
+                compile(body.clone())
+            ),
+        });
+        lines.push("break;".into());
+
+        cases.push(format!(
+            "{} {{\n{}}}",
+            label,
+            indent(
+                &lines
+                    .into_iter()
+                    .filter(|line| !line.is_empty())
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+                4
+            )
+        ));
+    }
+
+    // The tag is always one of the variants, the default case only tells the C compiler so.
+    if !wildcard {
+        cases.push("default:\n    abort();".into());
+    }
+    // `arlang_match` cannot clash with program names, which are prefixed; a nested `match`
+    // declares its own in an inner block.
+    let switch = format!(
+        "{{\n{}}}",
+        indent(
+            &format!(
+                "{} arlang_match = {};\nswitch (arlang_match.tag) {{\n{}}}",
+                c_type_of(scrutinee_type),
+                compile(*scrutinee.clone()),
+                cases
+                    .iter()
+                    .map(|case| format!("{}\n", case))
+                    .collect::<String>()
+            ),
+            4
+        )
+    );
+    if statement {
+        switch
+    } else {
+        format!(
+            "{} {};\n{}",
+            c_type_of(resolved_type),
+            match_value_name(*span),
+            switch
+        )
+    }
+}
+
+/// The label right after the loop at `span`, which `break` jumps to from inside a `match`.
+fn break_label(span: Span) -> String {
+    format!("arlang_break_{}_{}", span.line, span.col)
+}
+
+/// The label after the loop at `span`, if a `break` in its body needs one.
+fn loop_end(span: Span, body: &[Node]) -> String {
+    fn breaks_to(node: &Node, span: Span) -> bool {
+        match node {
+            // The span of the loop keyword, the loop itself also covers its body.
+            Node::Break { loop_span, .. } => {
+                loop_span.is_some_and(|loop_span| loop_span.start == span.start)
+            }
+            _ => node
+                .children()
+                .into_iter()
+                .any(|child| breaks_to(child, span)),
+        }
+    }
+
+    if body.iter().any(|stmt| breaks_to(stmt, span)) {
+        format!("\n{}:;", break_label(span))
+    } else {
+        String::new()
+    }
+}
+
+/// Maps the name of an arlang type to the C type that represents it.
+fn c_type_name(name: &str) -> &str {
+    match name {
+        "int" | "i64" => "int64_t",
+        "i32" => "int32_t",
+        "i16" => "int16_t",
+        "i8" => "int8_t",
+        "u64" => "uint64_t",
+        "u32" => "uint32_t",
+        "u16" => "uint16_t",
+        "u8" => "uint8_t",
+        "float" | "f64" => "double",
+        "f32" => "float",
+        "string" => "const char*",
+        "void" => "void",
+        _ => name,
+    }
+}
+
+/// Writes `val` as a C string literal, escaping everything that is not printable ASCII.
+fn c_string_literal(val: &str) -> String {
+    let mut output = String::from("\"");
+    for byte in val.bytes() {
+        match byte {
+            b'"' => output.push_str("\\\""),
+            b'\\' => output.push_str("\\\\"),
+            b'\n' => output.push_str("\\n"),
+            b'\t' => output.push_str("\\t"),
+            b'\r' => output.push_str("\\r"),
+            // `??` could start a trigraph.
+            b'?' => output.push_str("\\?"),
+            b' '..=b'~' => output.push(byte as char),
+            // Always three digits, so that a following digit is not read as part of the escape.
+            _ => output.push_str(&format!("\\{:03o}", byte)),
+        }
+    }
+    output.push('"');
+    output
+}
+
+fn c_type_of(typ: &Type) -> String {
+    match typ {
+        // C arrays cannot be assigned or returned, so every array type is wrapped in a struct.
+        Type::Array(element, len) => format!("arlang_array_{}_{}", mangle(element), len),
+        Type::Vec(element) => format!("{}*", vec_name(element)),
+        Type::Struct(name) | Type::Enum(name) => c_name(name),
+        _ => c_type_name(&typ.to_string()).into(),
+    }
+}
+
+/// The C struct behind a `Vec`, which also prefixes the runtime functions that work on it.
+fn vec_name(element: &Type) -> String {
+    format!("arlang_vec_{}", mangle(element))
+}
+
+/// A name for `typ` that can be part of a C identifier.
+fn mangle(typ: &Type) -> String {
+    match typ {
+        Type::Array(element, len) => format!("array_{}_{}", mangle(element), len),
+        Type::Vec(element) => format!("vec_{}", mangle(element)),
+        Type::Struct(name) | Type::Enum(name) => c_name(name),
+        _ => typ.to_string(),
+    }
+}
+
+fn c_type(typ: &Node) -> String {
+    c_type_of(&node_type(typ))
+}
+
+/// The type written by a type annotation; the type checker has already made sure it exists.
+fn node_type(typ: &Node) -> Type {
+    match typ {
+        Node::Identifier { name, .. } => {
+            Type::from_name(name).unwrap_or_else(|| Type::Struct(name.clone()))
+        }
+        Node::ArrayType { element, len, .. } => Type::Array(node_type(element).into(), *len),
+        // The type checker only accepts `Vec<T>`.
+        Node::GenericType { args, .. } => Type::Vec(node_type(&args[0]).into()),
+        _ => unreachable!("{:?} is not a type", typ),
+    }
+}
+
+/// The struct definitions of the program and the wrappers of the array types it uses,
+/// ordered so that every type comes after the types it contains by value, as C requires.
+/// `Vec`s are pointers, so they are declared up front and only instantiated at the end.
+fn type_definitions(program: &Node) -> Vec<String> {
+    fn add(typ: &Type, types: &mut Vec<Type>) {
+        if let Type::Array(element, _) | Type::Vec(element) = typ {
+            add(element, types);
+            // By C name, annotations name enums as structs.
+            if !types.iter().any(|other| c_type_of(other) == c_type_of(typ)) {
+                types.push(typ.clone());
+            }
+        }
+    }
+
+    fn collect(node: &Node, types: &mut Vec<Type>) {
+        let typ = match node {
+            Node::ArrayType { .. } | Node::GenericType { .. } => Some(node_type(node)),
+            Node::VarDecl { resolved_type, .. } | Node::ArrayLiteral { resolved_type, .. } => {
+                resolved_type.clone()
+            }
+            Node::MemberExpr { object_type, .. } => object_type.clone(),
+            Node::For { iter_type, .. } => iter_type.clone(),
+            Node::Match { resolved_type, .. } => resolved_type.clone(),
+            _ => None,
+        };
+        if let Some(typ) = typ {
+            add(&typ, types);
+        }
+
+        for child in node.children() {
+            collect(child, types);
+        }
+    }
+
+    fn visit(
+        typ: &Type,
+        definitions: &[&Node],
+        visited: &mut Vec<String>,
+        ordered: &mut Vec<String>,
+    ) {
+        // By C name, annotations name enums as structs.
+        let c_name = c_type_of(typ);
+        if visited.contains(&c_name) {
+            return;
+        }
+
+        match typ {
+            Type::Struct(name) | Type::Enum(name) => {
+                let Some(definition) = definitions.iter().find(|definition| {
+                    matches!(definition, Node::Struct { name: other, .. } | Node::Enum { name: other, .. } if other == name)
+                }) else {
+                    return;
+                };
+                visited.push(c_name);
+
+                let contained = match definition {
+                    Node::Struct { fields, .. } => fields
+                        .iter()
+                        .filter_map(|field| match field {
+                            Node::TypedIdentifier { typ, .. } => Some(typ.as_ref()),
+                            _ => None,
+                        })
+                        .collect(),
+                    Node::Enum { variants, .. } => variants
+                        .iter()
+                        .flat_map(|variant| variant.children())
+                        .collect(),
+                    _ => vec![],
+                };
+                for typ in contained {
+                    visit(&node_type(typ), definitions, visited, ordered);
+                }
+                ordered.push(compile((*definition).clone()));
+            }
+            Type::Array(element, len) => {
+                visited.push(c_name);
+                visit(element, definitions, visited, ordered);
+
+                let name = c_type_of(typ);
+                ordered.push(format!(
+                    "typedef struct {name} {{\n    {} items[{}];\n}} {name};",
+                    c_type_of(element),
+                    len
+                ));
+            }
+            _ => {}
+        }
+    }
+
+    let Node::Program { body, .. } = program else {
+        unreachable!()
+    };
+    let definitions = body
+        .iter()
+        .filter(|stmt| matches!(stmt, Node::Struct { .. } | Node::Enum { .. }))
+        .collect::<Vec<_>>();
+    let mut types = vec![];
+    collect(program, &mut types);
+
+    let vecs = types
+        .iter()
+        .filter_map(|typ| match typ {
+            Type::Vec(element) => Some(element.as_ref()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    let mut ordered = vecs
+        .iter()
+        .map(|element| format!("typedef struct {name} {name};", name = vec_name(element)))
+        .collect::<Vec<_>>();
+    let mut visited = vec![];
+    for definition in &definitions {
+        if let Node::Struct { name, .. } | Node::Enum { name, .. } = definition {
+            visit(
+                &Type::Struct(name.clone()),
+                &definitions,
+                &mut visited,
+                &mut ordered,
+            );
+        }
+    }
+    for typ in &types {
+        visit(typ, &definitions, &mut visited, &mut ordered);
+    }
+    ordered.extend(
+        vecs.iter()
+            .map(|element| format!("ARLANG_VEC({}, {})", c_type_of(element), vec_name(element))),
+    );
+
+    ordered
+}
+
+/// The C name of a name from the program. The prefix keeps them apart from C keywords, from
+/// the names the C library declares and from the names the compiler makes up, which all start
+/// with `arlang_`.
+fn c_name(name: &str) -> String {
+    format!("ar_{}", name)
+}
+
+/// The C name of a variable, or of a field when there is no symbol. The id of the symbol
+/// keeps apart the variables that share a name: C does not allow them in one scope, and it
+/// would read the new `x` in `let x = x + 1`. Program names cannot start with the digits.
+fn variable_name(name: &str, symbol: Option<usize>) -> String {
+    match symbol {
+        Some(id) => format!("ar_{}_{}", id, name),
+        None => c_name(name),
+    }
+}
+
+/// The C name of the tag of `variant`; the name of the enum is preceded by its length, to keep
+/// `A::B_C` and `A_B::C` apart.
+fn tag_name(name: &str, variant: &str) -> String {
+    format!("arlang_tag_{}{}_{}", name.len(), name, variant)
+}
+
+/// The C name of a function, or of the instantiation of a generic function for `type_args`.
+fn function_name(name: &str, type_args: &[Type]) -> String {
+    if type_args.is_empty() {
+        return c_name(name);
+    }
+
+    // Every part is preceded by its length, so that different instantiations cannot end up
+    // with the same name, whatever the names of the function and the types.
+    let mut output = format!("arlang_{}{}", name.len(), name);
+    for typ in type_args {
+        let typ = mangle(typ);
+        output.push_str(&format!("_{}{}", typ.len(), typ));
+    }
+    output
+}
+
+fn compile_signature(function: &Node) -> String {
+    let Node::Function {
+        name,
+        type_args,
+        params,
+        return_type,
+        ..
+    } = function
+    else {
+        unreachable!()
+    };
+
+    let params = if params.is_empty() {
+        "void".into()
+    } else {
+        params
+            .iter()
+            .map(|p| compile(p.clone()))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+
+    let return_type = match return_type {
+        Some(typ) => c_type(typ),
+        None => "void".into(),
+    };
+
+    format!(
+        "{} {}({})",
+        return_type,
+        function_name(name, type_args),
+        params
+    )
+}
@@ -0,0 +1,177 @@
+use crate::types::Span;
+use colored::*;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Level {
+    Error,
+    Warning,
+}
+
+/// A message about the source code, rendered rustc-style with the offending line underlined.
+///
+/// Error codes are grouped by the stage that produces them:
+/// `E00xx` lexer, `E01xx` parser, `E02xx` resolver, `E03xx` type checker.
+/// Warnings use a `W` prefix instead.
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub level: Level,
+    pub code: &'static str,
+    pub message: String,
+    pub span: Span,
+    pub label: Option<String>,
+    pub notes: Vec<String>,
+    pub help: Vec<String>,
+}
+
+impl Diagnostic {
+    pub fn new(level: Level, code: &'static str, message: impl Into<String>, span: Span) -> Self {
+        Self {
+            level,
+            code,
+            message: message.into(),
+            span,
+            label: None,
+            notes: vec![],
+            help: vec![],
+        }
+    }
+
+    pub fn error(code: &'static str, message: impl Into<String>, span: Span) -> Self {
+        Self::new(Level::Error, code, message, span)
+    }
+
+    pub fn warning(code: &'static str, message: impl Into<String>, span: Span) -> Self {
+        Self::new(Level::Warning, code, message, span)
+    }
+
+    /// Text printed right after the caret underline.
+    pub fn with_label(mut self, label: impl Into<String>) -> Self {
+        self.label = Some(label.into());
+        self
+    }
+
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    pub fn with_help(mut self, help: impl Into<String>) -> Self {
+        self.help.push(help.into());
+        self
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.level == Level::Error
+    }
+
+    pub fn render(&self, file_name: &str, source: &str) -> String {
+        let mut out = String::new();
+
+        let level = match self.level {
+            Level::Error => format!("error[{}]", self.code).red().bold(),
+            Level::Warning => format!("warning[{}]", self.code).yellow().bold(),
+        };
+        out.push_str(&format!(
+            "{}{} {}\n",
+            level,
+            ":".bold(),
+            self.message.bold()
+        ));
+
+        let line_no = self.span.line.max(1);
+        let gutter = " ".repeat(line_no.to_string().len());
+        let bar = "|".bright_blue().bold();
+
+        out.push_str(&format!(
+            "{}{} {}:{}:{}\n",
+            gutter,
+            "-->".bright_blue().bold(),
+            file_name,
+            line_no,
+            self.span.col.max(1)
+        ));
+        out.push_str(&format!("{} {}\n", gutter, bar));
+
+        let line = source.lines().nth(line_no - 1).unwrap_or("");
+        out.push_str(&format!(
+            "{} {} {}\n",
+            line_no.to_string().bright_blue().bold(),
+            bar,
+            line
+        ));
+
+        // Keep tabs so that the caret lines up with the source line.
+        let padding = line
+            .chars()
+            .take(self.span.col.saturating_sub(1))
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect::<String>();
+        let available = line.chars().count().saturating_sub(padding.chars().count());
+        let width = source
+            .get(self.span.start..self.span.end)
+            .map(|text| text.chars().take_while(|c| *c != '\n').count())
+            .unwrap_or(0)
+            .min(available)
+            .max(1);
+
+        let carets = "^".repeat(width);
+        let carets = match self.level {
+            Level::Error => carets.red().bold(),
+            Level::Warning => carets.yellow().bold(),
+        };
+        let label = match &self.label {
+            Some(label) => match self.level {
+                Level::Error => format!(" {}", label.red().bold()),
+                Level::Warning => format!(" {}", label.yellow().bold()),
+            },
+            None => String::new(),
+        };
+        out.push_str(&format!(
+            "{} {} {}{}{}\n",
+            gutter, bar, padding, carets, label
+        ));
+
+        if !self.notes.is_empty() || !self.help.is_empty() {
+            out.push_str(&format!("{} {}\n", gutter, bar));
+        }
+        for note in &self.notes {
+            out.push_str(&format!(
+                "{} {} {}: {}\n",
+                gutter,
+                "=".bright_blue().bold(),
+                "note".bold(),
+                note
+            ));
+        }
+        for help in &self.help {
+            out.push_str(&format!(
+                "{} {} {}: {}\n",
+                gutter,
+                "=".bright_blue().bold(),
+                "help".bold(),
+                help
+            ));
+        }
+
+        out
+    }
+}
+
+/// Prints every diagnostic to stderr, followed by a summary line when there were errors.
+pub fn report(diagnostics: &[Diagnostic], file_name: &str, source: &str) {
+    for diagnostic in diagnostics {
+        eprintln!("{}", diagnostic.render(file_name, source));
+    }
+
+    let errors = diagnostics.iter().filter(|d| d.is_error()).count();
+    match errors {
+        0 => {}
+        1 => eprintln!("{}", "error: aborting due to previous error".red().bold()),
+        n => eprintln!(
+            "{}",
+            format!("error: aborting due to {} previous errors", n)
+                .red()
+                .bold()
+        ),
+    }
+}
@@ -1,69 +1,148 @@
-use crate::types::Node::Identifier;
-use crate::types::{TokeType, Token};
+use crate::diagnostics::Diagnostic;
+use crate::types::{Span, TokeType, Token};
 
 macro_rules! parse_operator {
     ($chars: ident, $idx: ident) => {
-        if $chars.len() > $idx && $chars[$idx + 1] == '=' {
+        if $idx + 1 < $chars.len() && $chars[$idx + 1] == '=' {
             $idx += 1;
-            Some(Token {
-                typ: TokeType::Assignment,
-                val: format!("{}=", $chars[$idx - 1]),
-            })
+            Some((TokeType::Assignment, format!("{}=", $chars[$idx - 1])))
         } else {
-            Some(Token {
-                typ: TokeType::Operator,
-                val: $chars[$idx].into(),
-            })
+            Some((TokeType::Operator, $chars[$idx].into()))
         }
     };
 }
 
-pub(crate) fn lex(text: &str) -> Vec<Token> {
+#[derive(Debug, Clone)]
+pub enum LexErrorKind {
+    UnterminatedString,
+    UnterminatedComment,
+    UnexpectedChar(char),
+    InvalidSuffix(String),
+    MissingDigits,
+    IntegerTooLarge,
+}
+
+#[derive(Debug, Clone)]
+pub struct LexError {
+    pub kind: LexErrorKind,
+    pub span: Span,
+}
+
+impl From<LexError> for Diagnostic {
+    fn from(err: LexError) -> Self {
+        match err.kind {
+            LexErrorKind::UnterminatedString => {
+                Diagnostic::error("E0001", "unterminated string literal", err.span)
+                    .with_help("add a closing `\"` to the end of the string")
+            }
+            LexErrorKind::UnterminatedComment => {
+                Diagnostic::error("E0002", "unterminated block comment", err.span)
+                    .with_label("comment starts here")
+                    .with_help("close the comment with `*/`")
+            }
+            LexErrorKind::UnexpectedChar(char) => Diagnostic::error(
+                "E0003",
+                format!("unexpected character `{}`", char.escape_debug()),
+                err.span,
+            ),
+            LexErrorKind::InvalidSuffix(suffix) => Diagnostic::error(
+                "E0004",
+                format!("invalid suffix `{}` for number literal", suffix),
+                err.span,
+            )
+            .with_help(
+                "the suffix must be one of `i8`, `i16`, `i32`, `i64`, `u8`, `u16`, `u32`, `u64`, \
+                 `f32` or `f64`, and only decimal literals can be floats",
+            ),
+            LexErrorKind::MissingDigits => {
+                Diagnostic::error("E0005", "no digits in number literal", err.span)
+            }
+            LexErrorKind::IntegerTooLarge => {
+                Diagnostic::error("E0006", "integer literal is too large", err.span)
+                    .with_note("the largest integer literal is `18446744073709551615`")
+            }
+        }
+    }
+}
+
+/// Splits the source into tokens.
+///
+/// Lexing does not stop at the first problem: invalid input is skipped so that every
+/// lexical error in the text is reported at once.
+pub(crate) fn lex(text: &str) -> Result<Vec<Token>, Vec<LexError>> {
     let chars = text.chars().collect::<Vec<char>>();
     let mut tokens: Vec<Token> = vec![];
+    let mut errors: Vec<LexError> = vec![];
     let mut idx = 0;
 
+    // (byte offset, line, column) of every char, plus one extra entry for the end of the input.
+    let mut positions = Vec::with_capacity(chars.len() + 1);
+    let (mut line, mut col) = (1, 1);
+    for (offset, char) in text.char_indices() {
+        positions.push((offset, line, col));
+        if char == '\n' {
+            line += 1;
+            col = 1;
+        } else {
+            col += 1;
+        }
+    }
+    positions.push((text.len(), line, col));
+
+    let span = |start: usize, end: usize| {
+        let (offset, line, col) = positions[start];
+        Span {
+            start: offset,
+            end: positions[end.min(chars.len())].0,
+            line,
+            col,
+        }
+    };
+
     while idx < chars.len() {
+        let start = idx;
         let char: char = chars[idx];
         let val: String = char.into();
 
         let tok = match char {
-            '(' => Some(Token {
-                typ: TokeType::OpenParen,
-                val,
-            }),
-            '=' => Some(Token {
-                typ: TokeType::Assignment,
-                val,
-            }),
-            ')' => Some(Token {
-                typ: TokeType::CloseParen,
-                val,
-            }),
-            '[' => Some(Token {
-                typ: TokeType::OpenBracket,
-                val,
-            }),
-            ']' => Some(Token {
-                typ: TokeType::CloseBracket,
-                val,
-            }),
-            '{' => Some(Token {
-                typ: TokeType::OpenBrace,
-                val,
-            }),
-            '}' => Some(Token {
-                typ: TokeType::CloseBrace,
-                val,
-            }),
-            ',' => Some(Token {
-                typ: TokeType::Comma,
-                val,
-            }),
-            ':' => Some(Token {
-                typ: TokeType::Colon,
-                val,
-            }),
+            '(' => Some((TokeType::OpenParen, val)),
+            '=' if chars.get(idx + 1) == Some(&'=') => {
+                idx += 1;
+                Some((TokeType::Operator, "==".into()))
+            }
+            '=' if chars.get(idx + 1) == Some(&'>') => {
+                idx += 1;
+                Some((TokeType::FatArrow, "=>".into()))
+            }
+            '=' => Some((TokeType::Assignment, val)),
+            '!' | '<' | '>' => {
+                if chars.get(idx + 1) == Some(&'=') {
+                    idx += 1;
+                    Some((TokeType::Operator, format!("{}=", char)))
+                } else {
+                    Some((TokeType::Operator, val))
+                }
+            }
+            '&' | '|' if chars.get(idx + 1) == Some(&char) => {
+                idx += 1;
+                Some((TokeType::Operator, format!("{}{}", char, char)))
+            }
+            ')' => Some((TokeType::CloseParen, val)),
+            '[' => Some((TokeType::OpenBracket, val)),
+            ']' => Some((TokeType::CloseBracket, val)),
+            '{' => Some((TokeType::OpenBrace, val)),
+            '}' => Some((TokeType::CloseBrace, val)),
+            ',' => Some((TokeType::Comma, val)),
+            ':' if chars.get(idx + 1) == Some(&':') => {
+                idx += 1;
+                Some((TokeType::DoubleColon, "::".into()))
+            }
+            ':' => Some((TokeType::Colon, val)),
+            ';' => Some((TokeType::Semicolon, val)),
+            '-' if chars.get(idx + 1) == Some(&'>') => {
+                idx += 1;
+                Some((TokeType::Arrow, "->".into()))
+            }
             '-' => parse_operator!(chars, idx),
             '*' => parse_operator!(chars, idx),
             '+' => parse_operator!(chars, idx),
@@ -71,132 +150,94 @@ pub(crate) fn lex(text: &str) -> Vec<Token> {
             '"' => {
                 let mut string_chars: Vec<char> = vec![];
                 idx += 1;
-                let mut prev = chars.get(idx).unwrap_or(&'.');
 
-                while idx < chars.len() {
-                    if chars[idx] == '"' {
-                        if prev != &'\\' {
-                            break;
-                        }
-                    }
-
-                    if prev == &'\\' {
+                while idx < chars.len() && chars[idx] != '"' {
+                    if chars[idx] == '\\' && idx + 1 < chars.len() {
+                        idx += 1;
                         match chars[idx] {
                             // Replaces some escaped characters with their actual value.
-                            'n' => {
-                                string_chars.pop();
-                                string_chars.push('\n');
+                            'n' => string_chars.push('\n'),
+                            't' => string_chars.push('\t'),
+                            'r' => string_chars.push('\r'),
+                            '0' => string_chars.push('\0'),
+                            '"' => string_chars.push('"'),
+                            '\\' => string_chars.push('\\'),
+                            other => {
+                                string_chars.push('\\');
+                                string_chars.push(other);
                             }
-                            't' => {
-                                string_chars.pop();
-                                string_chars.push('\t');
-                            }
-                            'r' => {
-                                string_chars.pop();
-                                string_chars.push('\r');
-                            }
-                            '"' => {
-                                string_chars.pop();
-                                string_chars.push('"');
-                            }
-                            _ => string_chars.push(chars[idx]),
                         }
                     } else {
-                        string_chars.push(chars[idx])
+                        string_chars.push(chars[idx]);
                     }
-
-                    prev = &chars[idx];
                     idx += 1;
                 }
 
-                if chars[idx] != '"' {
-                    panic!("Missing quote from string: Did you forget to add a closing quote to the string?")
+                if idx < chars.len() {
+                    Some((TokeType::String, string_chars.iter().collect()))
+                } else {
+                    errors.push(LexError {
+                        kind: LexErrorKind::UnterminatedString,
+                        span: span(start, idx),
+                    });
+                    None
+                }
+            }
+            '/' if matches!(chars.get(idx + 1), Some('/')) => {
+                let mut comment_chars: Vec<char> = vec![];
+                idx += 2;
+
+                while idx < chars.len() && chars[idx] != '\n' {
+                    comment_chars.push(chars[idx]);
+                    idx += 1;
                 }
+                idx -= 1;
 
-                Some(Token {
-                    typ: TokeType::String,
-                    val: string_chars.iter().collect(),
-                })
+                Some((TokeType::Comment, comment_chars.into_iter().collect()))
             }
-            '/' => {
-                if let Some(next) = chars.get(idx + 1) {
-                    if next == &'*' || next == &'/' {
-                        let mut comment_chars: Vec<char> = vec![];
-                        let is_multiline = next == &'*';
-
-                        idx += 2;
-                        if idx >= chars.len() {
-                            unreachable!()
-                        }
-                        let mut prev = chars.get(idx).unwrap_or(&'.');
-
-                        while idx < chars.len()
-                            && ((is_multiline && (prev != &'*' && chars[idx] != '/'))
-                                || (!is_multiline && chars[idx] != '\n'))
-                        {
-                            comment_chars.push(chars[idx]);
-                            prev = &chars[idx];
-                            idx += 1;
-                        }
+            '/' if matches!(chars.get(idx + 1), Some('*')) => {
+                let mut comment_chars: Vec<char> = vec![];
+                idx += 2;
 
-                        if is_multiline {
-                            if chars[idx - 1] == '*' && chars[idx] == '/' {
-                                comment_chars.pop();
-                                idx += 1;
-                            } else {
-                                panic!("Multiline comment was not closed.")
-                            }
-                        }
-                        idx -= 1;
+                while idx < chars.len() && !(chars[idx] == '*' && chars.get(idx + 1) == Some(&'/'))
+                {
+                    comment_chars.push(chars[idx]);
+                    idx += 1;
+                }
 
-                        Some(Token {
-                            typ: TokeType::Comment,
-                            val: comment_chars.into_iter().collect(),
-                        })
-                    } else {
-                        parse_operator!(chars, idx)
-                    }
+                if idx < chars.len() {
+                    // Leave `idx` on the closing `/`.
+                    idx += 1;
+                    Some((TokeType::Comment, comment_chars.into_iter().collect()))
                 } else {
-                    unreachable!()
+                    errors.push(LexError {
+                        kind: LexErrorKind::UnterminatedComment,
+                        span: span(start, start + 2),
+                    });
+                    None
                 }
             }
+            '/' => parse_operator!(chars, idx),
             _ => {
-                if chars[idx].is_numeric() {
-                    let mut number_chars: Vec<char> = vec![chars[idx]];
-                    let mut is_float = false;
-                    idx += 1;
+                if chars[idx].is_ascii_digit() {
+                    let (end, number) = lex_number(&chars, idx);
+                    idx = end - 1;
 
-                    while idx < chars.len() && (chars[idx].is_numeric() || chars[idx] == '.') {
-                        if chars[idx] == '.' && is_float {
-                            idx -= 1;
-                            is_float = false;
-
-                            number_chars.pop();
-                            break;
-                        } else if chars[idx] == '.' {
-                            is_float = true;
+                    match number {
+                        Ok(tok) => Some(tok),
+                        Err(kind) => {
+                            errors.push(LexError {
+                                kind,
+                                span: span(start, end),
+                            });
+                            None
                         }
-
-                        number_chars.push(chars[idx]);
-                        idx += 1;
                     }
-
-                    idx -= 1;
-
-                    Some(Token {
-                        typ: (if is_float {
-                            TokeType::Float
-                        } else {
-                            TokeType::Int
-                        }),
-                        val: number_chars.into_iter().collect(),
-                    })
                 } else if idx + 1 < chars.len() && chars[idx] == '.' && chars[idx + 1] == '.' {
                     idx += 1;
-                    Some(Token {
-                        typ: TokeType::Operator,
-                        val: "Range".into(),
-                    })
+                    Some((TokeType::Operator, "Range".into()))
+                } else if chars[idx] == '.' {
+                    Some((TokeType::Operator, val))
                 } else if chars[idx].is_whitespace() {
                     None
                 } else if chars[idx].is_alphabetic() || chars[idx] == '_' {
@@ -211,30 +252,122 @@ pub(crate) fn lex(text: &str) -> Vec<Token> {
                     let word = identifier_or_keyword_chars.iter().collect::<String>();
 
                     match word.as_str() {
-                        "const" | "let" | "fn" => Some(Token {
-                            typ: TokeType::Keyword,
-                            val: word,
-                        }),
-                        _ => Some(Token {
-                            typ: TokeType::Identifier,
-                            val: word,
-                        }),
+                        "const" | "let" | "fn" | "struct" | "enum" | "if" | "else" | "while"
+                        | "for" | "in" | "break" | "continue" | "return" | "true" | "false"
+                        | "as" | "match" => Some((TokeType::Keyword, word)),
+                        _ => Some((TokeType::Identifier, word)),
                     }
                 } else {
-                    panic!(
-                        "Unexpected value found: {}",
-                        chars[idx..].into_iter().take(20).collect::<String>()
-                    )
+                    errors.push(LexError {
+                        kind: LexErrorKind::UnexpectedChar(chars[idx]),
+                        span: span(start, idx + 1),
+                    });
+                    None
                 }
             }
         };
 
         idx += 1;
 
-        if let Some(tok) = tok {
-            tokens.push(tok)
+        if let Some((typ, val)) = tok {
+            tokens.push(Token {
+                typ,
+                val,
+                span: span(start, idx),
+            })
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(tokens)
+    } else {
+        Err(errors)
+    }
+}
+
+const NUMBER_SUFFIXES: [&str; 10] = [
+    "i8", "i16", "i32", "i64", "u8", "u16", "u32", "u64", "f32", "f64",
+];
+
+/// Lexes the number literal starting at `start`, returning the index after it.
+///
+/// Integers are normalized to decimal, since C has no `0o` or `0b` prefixes, and underscores
+/// are dropped; the suffix, if any, is kept at the end of the value, as in `255u8`.
+fn lex_number(chars: &[char], start: usize) -> (usize, Result<(TokeType, String), LexErrorKind>) {
+    let mut idx = start;
+    let take_digits = |idx: &mut usize, radix: u32| {
+        let mut digits = String::new();
+        while *idx < chars.len() && (chars[*idx].is_digit(radix) || chars[*idx] == '_') {
+            if chars[*idx] != '_' {
+                digits.push(chars[*idx]);
+            }
+            *idx += 1;
         }
+        digits
+    };
+
+    let radix = match (chars[idx], chars.get(idx + 1)) {
+        ('0', Some('x')) => 16,
+        ('0', Some('o')) => 8,
+        ('0', Some('b')) => 2,
+        _ => 10,
+    };
+    if radix != 10 {
+        idx += 2;
     }
 
-    tokens
+    let mut number = take_digits(&mut idx, radix);
+    let mut is_float = false;
+
+    if radix == 10 {
+        // A `.` only continues the number when a digit follows, so `1..5` is a range.
+        if chars.get(idx) == Some(&'.') && chars.get(idx + 1).is_some_and(|c| c.is_ascii_digit()) {
+            idx += 1;
+            number.push('.');
+            number.push_str(&take_digits(&mut idx, 10));
+            is_float = true;
+        }
+
+        if matches!(chars.get(idx), Some('e' | 'E')) {
+            let sign = matches!(chars.get(idx + 1), Some('+' | '-'));
+            let exponent = idx + 1 + sign as usize;
+            if chars.get(exponent).is_some_and(|c| c.is_ascii_digit()) {
+                number.push('e');
+                if sign {
+                    number.push(chars[idx + 1]);
+                }
+                idx = exponent;
+                number.push_str(&take_digits(&mut idx, 10));
+                is_float = true;
+            }
+        }
+    }
+
+    let suffix_start = idx;
+    while idx < chars.len() && (chars[idx].is_alphanumeric() || chars[idx] == '_') {
+        idx += 1;
+    }
+    let suffix = chars[suffix_start..idx].iter().collect::<String>();
+
+    if number.is_empty() {
+        return (idx, Err(LexErrorKind::MissingDigits));
+    }
+
+    let float_suffix = suffix.starts_with('f');
+    if !suffix.is_empty()
+        && (!NUMBER_SUFFIXES.contains(&suffix.as_str())
+            || (is_float && !float_suffix)
+            || (radix != 10 && float_suffix))
+    {
+        return (idx, Err(LexErrorKind::InvalidSuffix(suffix)));
+    }
+
+    if is_float || float_suffix {
+        return (idx, Ok((TokeType::Float, number + &suffix)));
+    }
+
+    match u64::from_str_radix(&number, radix) {
+        Ok(value) => (idx, Ok((TokeType::Int, value.to_string() + &suffix))),
+        Err(_) => (idx, Err(LexErrorKind::IntegerTooLarge)),
+    }
 }
@@ -3,8 +3,13 @@
 extern crate core;
 
 mod compiler;
+mod diagnostics;
 mod lexer;
+mod monomorphize;
 mod parser;
+mod resolve;
+mod runtime;
+mod typeck;
 mod types;
 
 use crate::types::Node;
@@ -21,6 +26,32 @@ pub fn indent(s: &str, n: usize) -> String {
     out
 }
 
+// Runs the front-end over `source`, printing any diagnostics; returns the AST if there were no errors.
+fn check_source(file_name: &str, source: &str) -> Option<Node> {
+    let tokens = match lexer::lex(source) {
+        Ok(tokens) => tokens,
+        Err(errors) => {
+            let diagnostics = errors.into_iter().map(Into::into).collect::<Vec<_>>();
+            diagnostics::report(&diagnostics, file_name, source);
+            return None;
+        }
+    };
+
+    let (mut program, mut diagnostics) = parser::Parser::new(tokens).parse();
+    // Checking a partial program would only report follow-up errors.
+    if !diagnostics.iter().any(|d| d.is_error()) {
+        diagnostics.extend(resolve::resolve(&mut program));
+        diagnostics.extend(typeck::check(&mut program));
+    }
+    diagnostics::report(&diagnostics, file_name, source);
+
+    if diagnostics.iter().any(|d| d.is_error()) {
+        None
+    } else {
+        Some(program)
+    }
+}
+
 fn repl() {
     let mut rl = DefaultEditor::new().unwrap();
 
@@ -28,23 +59,15 @@ fn repl() {
         let readline = rl.readline("> ");
         match readline {
             Ok(line) => {
-                if line.trim().len() == 0 || line.trim() == "exit" {
+                if line.trim().is_empty() || line.trim() == "exit" {
                     println!("Exiting...");
                     break;
                 }
 
                 rl.add_history_entry(line.as_str()).unwrap();
-                let parser = parser::Parser::new(lexer::lex(&format!(
-                    "fn main() {{\n{}\n}}",
-                    indent(&line, 4)
-                )))
-                .parse();
-
-                match &parser {
-                    Node::Program { body } => {
-                        println!("{:#?}", body);
-                    }
-                    _ => unreachable!(),
+                let source = format!("fn main() {{\n{}\n}}", indent(&line, 4));
+                if let Some(Node::Program { body, .. }) = check_source("<repl>", &source) {
+                    println!("{:#?}", body);
                 }
             }
             Err(ReadlineError::Interrupted) => {
@@ -77,30 +100,56 @@ fn main() -> Result<()> {
 
     let mut input = String::from("1 + 1 + 1 / 5");
 
-    match args.next() {
-        Some(arg) => match arg.as_str() {
-            "repl" => return Ok(repl()),
+    if let Some(arg) = args.next() {
+        match arg.as_str() {
+            "repl" => {
+                repl();
+                return Ok(());
+            }
             "compile" => {
                 input = args.collect::<Vec<_>>().join(" ");
             }
+            "build" => {
+                let usage = "Usage: arlang build <file> [-o <output>] [--no-bounds-checks]";
+                let Some(path) = args.next() else {
+                    println!("{}", usage);
+                    return Ok(());
+                };
+
+                let mut output = std::path::Path::new(&path).with_extension("c");
+                let mut options = compiler::Options::default();
+                while let Some(arg) = args.next() {
+                    match (arg.as_str(), args.len()) {
+                        ("-o", 1..) => output = args.next().unwrap().into(),
+                        ("--no-bounds-checks", _) => options.bounds_checks = false,
+                        _ => {
+                            println!("{}", usage);
+                            return Ok(());
+                        }
+                    }
+                }
+
+                let source = std::fs::read_to_string(&path)?;
+                let Some(program) = check_source(&path, &source) else {
+                    std::process::exit(1);
+                };
+
+                std::fs::write(&output, compiler::compile_program(program, &options))?;
+                println!("Wrote {}", output.display());
+                return Ok(());
+            }
             _ => {
                 println!("Unknown argument: {}", arg);
                 return Ok(());
             }
-        },
-        None => {}
+        }
     }
 
-    print!("> {input}\n");
+    println!("> {input}");
 
-    let parser = parser::Parser::new(lexer::lex(&input)).parse();
-
-    match &parser {
-        Node::Program { body } => {
-            let code = compiler::compile(parser);
-            println!("Generated code:\n\n{}", code);
-        }
-        _ => unreachable!(),
+    if let Some(program) = check_source("<input>", &input) {
+        let code = compiler::compile(program);
+        println!("Generated code:\n\n{}", code);
     }
 
     Ok(())
@@ -0,0 +1,121 @@
+use crate::types::{Node, Span, Type};
+use std::collections::HashMap;
+
+/// A copy of the generic function `template` with every type parameter in its annotations
+/// replaced by its type argument, so that it can be checked and compiled like any other function.
+/// `ordered` holds the same type arguments, in the order of the type parameters.
+pub(crate) fn instantiate(
+    template: &Node,
+    type_args: &HashMap<String, Type>,
+    ordered: Vec<Type>,
+) -> Node {
+    let mut function = template.clone();
+    if let Node::Function {
+        type_params,
+        type_args,
+        ..
+    } = &mut function
+    {
+        type_params.clear();
+        *type_args = ordered;
+    }
+    substitute(&mut function, type_args);
+    function
+}
+
+/// Binds the type parameters in `param` to the parts of `arg` in the same place, so that
+/// `Vec<T>` and `Vec<int>` give `T = int`; the first binding of a parameter wins.
+pub(crate) fn infer(param: &Type, arg: &Type, type_args: &mut HashMap<String, Type>) {
+    match (param, arg) {
+        (_, Type::Error) => {}
+        (Type::Param(name), _) => {
+            type_args.entry(name.clone()).or_insert_with(|| arg.clone());
+        }
+        (Type::Array(param, len), Type::Array(arg, arg_len)) if len == arg_len => {
+            infer(param, arg, type_args)
+        }
+        (Type::Vec(param), Type::Vec(arg)) => infer(param, arg, type_args),
+        _ => {}
+    }
+}
+
+/// `typ` with the type parameters that are bound in `type_args` replaced.
+pub(crate) fn substitute_type(typ: &Type, type_args: &HashMap<String, Type>) -> Type {
+    match typ {
+        Type::Param(name) => type_args.get(name).cloned().unwrap_or_else(|| typ.clone()),
+        Type::Array(element, len) => Type::Array(substitute_type(element, type_args).into(), *len),
+        Type::Vec(element) => Type::Vec(substitute_type(element, type_args).into()),
+        _ => typ.clone(),
+    }
+}
+
+/// The type parameters that `typ` is built from.
+pub(crate) fn type_params_of(typ: &Type) -> Vec<&str> {
+    match typ {
+        Type::Param(name) => vec![name],
+        Type::Array(element, _) | Type::Vec(element) => type_params_of(element),
+        _ => vec![],
+    }
+}
+
+fn substitute(node: &mut Node, type_args: &HashMap<String, Type>) {
+    match node {
+        Node::TypedIdentifier {
+            typ: annotation, ..
+        }
+        | Node::Cast {
+            typ: annotation, ..
+        }
+        | Node::VarDecl {
+            type_annotation: Some(annotation),
+            ..
+        }
+        | Node::Function {
+            return_type: Some(annotation),
+            ..
+        } => substitute_annotation(annotation, type_args),
+        _ => {}
+    }
+
+    for child in node.children_mut() {
+        substitute(child, type_args);
+    }
+}
+
+fn substitute_annotation(typ: &mut Node, type_args: &HashMap<String, Type>) {
+    match typ {
+        Node::Identifier { name, span, .. } => {
+            if let Some(arg) = type_args.get(name) {
+                *typ = annotation(arg, *span);
+            }
+        }
+        Node::ArrayType { element, .. } => substitute_annotation(element, type_args),
+        Node::GenericType { args, .. } => {
+            for arg in args {
+                substitute_annotation(arg, type_args);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// The type annotation that names `typ`.
+fn annotation(typ: &Type, span: Span) -> Node {
+    match typ {
+        Type::Array(element, len) => Node::ArrayType {
+            element: annotation(element, span).into(),
+            len: *len,
+            span,
+        },
+        Type::Vec(element) => Node::GenericType {
+            name: "Vec".into(),
+            args: vec![annotation(element, span)],
+            span,
+        },
+        _ => Node::Identifier {
+            name: typ.to_string(),
+            symbol: None,
+            span,
+        },
+    }
+}
@@ -1,71 +1,767 @@
-use crate::types::{Node, Program, TokeType, Token};
-use anyhow::{bail, Result};
+use crate::diagnostics::Diagnostic;
+use crate::types::{Node, Program, Span, TokeType, Token};
+
+type Result<T> = std::result::Result<T, Box<Diagnostic>>;
+
+/// Keywords that start a statement; error recovery resumes parsing at these.
+const STATEMENT_KEYWORDS: [&str; 11] = [
+    "fn", "struct", "enum", "let", "const", "if", "while", "for", "break", "continue", "return",
+];
 
 pub struct Parser {
     pub tokens: Vec<Token>,
+    /// Zero-width span right after the last token, used to report a premature end of input.
+    eof: Span,
+    errors: Vec<Diagnostic>,
+    /// The loops that enclose the statement being parsed, innermost last, to validate
+    /// `break` and `continue`.
+    loops: Vec<Span>,
+    /// How many `match` arms enclose the statement being parsed inside the innermost loop.
+    match_depth: usize,
+    /// Set in the expression before a block, e.g. the condition of an `if`, where `name {`
+    /// starts the block instead of a struct literal.
+    no_struct_literal: bool,
+    /// The line of the last consumed token, where error recovery starts.
+    line: usize,
+    /// Set once a top-level `fn main` header is reached, so a `main` whose body fails to parse is
+    /// not also reported as missing.
+    saw_main: bool,
 }
 
 impl Parser {
     pub fn new(tokens: Vec<Token>) -> Self {
-        Self { tokens }
+        let eof = tokens
+            .last()
+            .map(|tok| Span {
+                start: tok.span.end,
+                end: tok.span.end,
+                line: tok.span.line,
+                col: tok.span.col + (tok.span.end - tok.span.start),
+            })
+            .unwrap_or(Span {
+                start: 0,
+                end: 0,
+                line: 1,
+                col: 1,
+            });
+
+        // Comments carry no meaning for the parser.
+        let tokens = tokens
+            .into_iter()
+            .filter(|tok| !matches!(tok.typ, TokeType::Comment))
+            .collect();
+
+        Self {
+            tokens,
+            eof,
+            errors: vec![],
+            loops: vec![],
+            match_depth: 0,
+            no_struct_literal: false,
+            line: 1,
+            saw_main: false,
+        }
     }
 
-    pub fn parse(&mut self) -> Node {
-        let mut program = Node::Program { body: vec![] };
-        let Node::Program { mut body } = program else {
-            unreachable!()
+    /// Parses the whole program.
+    ///
+    /// Parsing continues past syntax errors, so the returned program may be partial;
+    /// it is only safe to use when the list of diagnostics is empty.
+    pub fn parse(&mut self) -> (Node, Vec<Diagnostic>) {
+        let span = match (self.tokens.first(), self.tokens.last()) {
+            (Some(first), Some(last)) => first.span.to(last.span),
+            _ => self.eof,
         };
+        let mut body = vec![];
 
         while !self.eof() {
-            body.push(self.parse_additive_expr().unwrap());
+            self.saw_main |= matches!(
+                &self.tokens[..],
+                [fn_tok, name, ..] if fn_tok.val == "fn" && name.val == "main"
+                    && matches!(fn_tok.typ, TokeType::Keyword)
+                    && matches!(name.typ, TokeType::Identifier)
+            );
+            if let Some(stmt) = self.parse_stmt_or_recover() {
+                body.push(stmt);
+            }
+        }
+
+        if !self.saw_main {
+            self.errors.push(
+                Diagnostic::error("E0104", "no `main` function found", span)
+                    .with_help("add `fn main() { }` as the entry point of the program"),
+            );
         }
 
-        let found_main = !body
-            .iter()
-            .filter(|x| {
-                if let Node::Function { name, params, body } = x {
-                    if name == "main" {
-                        return true;
+        (
+            Node::Program { body, span },
+            std::mem::take(&mut self.errors),
+        )
+    }
+
+    /// Parses a statement, recording the error and skipping to the next statement
+    /// boundary if it is malformed.
+    fn parse_stmt_or_recover(&mut self) -> Option<Node> {
+        let remaining = self.tokens.len();
+
+        match self.parse_stmt() {
+            Ok(stmt) => Some(stmt),
+            Err(err) => {
+                self.errors.push(*err);
+                // Always make progress, otherwise we would report the same error forever.
+                // A stray `{` is skipped together with the group it opens.
+                let mut depth = 0;
+                if self.tokens.len() == remaining {
+                    if let Ok(tok) = self.consume() {
+                        if tok.typ == TokeType::OpenBrace {
+                            depth = 1;
+                        }
                     }
                 }
-                false
-            })
-            .collect::<Vec<_>>()
-            .is_empty();
-
-        if !found_main {
-            panic!("No main function found");
+                self.synchronize(depth);
+                None
+            }
         }
+    }
+
+    /// Skips tokens until something that can start a new statement: a statement keyword,
+    /// the first token on a later line, or the end of the current block.
+    ///
+    /// Nested `{ ... }` groups are skipped as a whole, so a malformed function header
+    /// does not cause its body to be parsed as top-level code; `depth` is the number of
+    /// groups that are open already.
+    fn synchronize(&mut self, mut depth: usize) {
+        let line = self.line;
 
-        Node::Program {
-            body: body.to_vec(),
+        while let Some(tok) = self.tokens.first() {
+            match tok.typ {
+                TokeType::CloseBrace if depth == 0 => return,
+                _ if depth == 0 && tok.span.line != line => return,
+                TokeType::Keyword
+                    if depth == 0 && STATEMENT_KEYWORDS.contains(&tok.val.as_str()) =>
+                {
+                    return
+                }
+                TokeType::OpenBrace => depth += 1,
+                TokeType::CloseBrace => {
+                    depth -= 1;
+                    if depth == 0 {
+                        self.consume().ok();
+                        return;
+                    }
+                }
+                _ => {}
+            }
+            self.consume().ok();
         }
     }
 
     fn eof(&self) -> bool {
-        self.tokens.len() == 0
+        self.tokens.is_empty()
     }
 
     fn at(&mut self) -> Result<&Token> {
         let first = self.tokens.first();
 
-        first.ok_or_else(|| anyhow::anyhow!("Unexpected end of input"))
+        first.ok_or_else(|| Diagnostic::error("E0100", "unexpected end of input", self.eof).into())
     }
 
     fn consume(&mut self) -> Result<Token> {
         let tok = self.at()?.clone();
         // println!("\nConsuming token: {:?}", tok);
         self.tokens = self.tokens[1..].to_vec();
+        self.line = tok.span.line;
         // println!("Tokens: {:?}\n", self.tokens);
         Ok(tok)
     }
 
-    fn expect(&mut self, typ: TokeType) -> Result<Token> {
-        let tok = self.consume()?;
-        if !matches!(&tok.typ, typ) {
-            bail!("lol, this might be a parser bug");
+    /// Consumes the next token if it is of the given type, otherwise reports what was
+    /// expected `context` (e.g. "after parameter name") and leaves the token in place.
+    fn expect(&mut self, typ: TokeType, context: &str) -> Result<Token> {
+        let Some(tok) = self.tokens.first() else {
+            return Err(Diagnostic::error(
+                "E0103",
+                format!(
+                    "expected {} {}, found end of input",
+                    typ.describe(),
+                    context
+                ),
+                self.eof,
+            )
+            .into());
+        };
+
+        if tok.typ != typ {
+            return Err(Diagnostic::error(
+                "E0103",
+                format!("expected {} {}, found {}", typ.describe(), context, tok),
+                tok.span,
+            )
+            .with_label(format!("expected {}", typ.describe()))
+            .into());
         }
-        Ok(tok)
+
+        self.consume()
+    }
+
+    /// Like `expect`, but for a specific keyword.
+    fn expect_keyword(&mut self, keyword: &str, context: &str) -> Result<Token> {
+        self.expect_token(TokeType::Keyword, keyword, context)
+    }
+
+    /// Like `expect`, but for a specific operator.
+    fn expect_operator(&mut self, operator: &str, context: &str) -> Result<Token> {
+        self.expect_token(TokeType::Operator, operator, context)
+    }
+
+    fn expect_token(&mut self, typ: TokeType, val: &str, context: &str) -> Result<Token> {
+        match self.tokens.first() {
+            Some(tok) if tok.typ == typ && tok.val == val => self.consume(),
+            Some(tok) => Err(Diagnostic::error(
+                "E0103",
+                format!("expected `{}` {}, found {}", val, context, tok),
+                tok.span,
+            )
+            .with_label(format!("expected `{}`", val))
+            .into()),
+            None => Err(Diagnostic::error(
+                "E0103",
+                format!("expected `{}` {}, found end of input", val, context),
+                self.eof,
+            )
+            .into()),
+        }
+    }
+
+    fn parse_stmt(&mut self) -> Result<Node> {
+        let tok = self.at()?;
+        if !matches!(tok.typ, TokeType::Keyword) {
+            return self.parse_expr();
+        }
+
+        match tok.val.as_str() {
+            "const" | "let" => self.parse_var_decl(),
+            "fn" => self.parse_fn(),
+            "struct" => self.parse_struct(),
+            "enum" => self.parse_enum(),
+            "if" => self.parse_if(),
+            "while" => self.parse_while(),
+            "for" => self.parse_for(),
+            "break" | "continue" => self.parse_loop_control(),
+            "return" => self.parse_return(),
+            _ => self.parse_expr(),
+        }
+    }
+
+    fn parse_var_decl(&mut self) -> Result<Node> {
+        use TokeType::*;
+
+        let node = self.consume()?;
+        let ident = self.expect(Identifier, &format!("after `{}`", node.val))?;
+
+        let mut type_annotation = None;
+        if matches!(self.at()?.typ, Colon) {
+            self.consume()?;
+            type_annotation = Some(self.parse_type()?.into());
+        }
+
+        let op = self.expect(Assignment, "after variable name")?;
+        if op.val != "=" {
+            return Err(Diagnostic::error(
+                "E0105",
+                format!("`{}` cannot be used when declaring a variable", op.val),
+                op.span,
+            )
+            .with_help("use `=` to give the variable its initial value")
+            .into());
+        }
+
+        let init = self.parse_expr()?;
+        Ok(Node::VarDecl {
+            span: node.span.to(init.span()),
+            name: ident.val,
+            mutable: node.val == "let",
+            type_annotation,
+            init: init.into(),
+            symbol: None,
+            resolved_type: None,
+        })
+    }
+
+    fn parse_fn(&mut self) -> Result<Node> {
+        use TokeType::*;
+
+        let node = self.consume()?;
+        let name = self.expect(Identifier, "after `fn`")?.val;
+
+        let mut type_params = vec![];
+        if !self.eof() && self.at()?.typ == Operator && self.at()?.val == "<" {
+            self.consume()?;
+            loop {
+                let ident = self.expect(Identifier, "as the type parameter name")?;
+                type_params.push(Node::Identifier {
+                    name: ident.val,
+                    symbol: None,
+                    span: ident.span,
+                });
+
+                if matches!(self.at()?.typ, Comma) {
+                    self.consume()?;
+                } else {
+                    break;
+                }
+            }
+            self.expect_operator(">", "to close the type parameters")?;
+        }
+
+        self.expect(OpenParen, "after function name")?;
+        let mut params = vec![];
+        while !self.eof() && matches!(self.at()?.typ, Identifier) {
+            let ident = self.consume()?;
+            self.expect(Colon, "after parameter name")?;
+
+            let typ = self.parse_type()?;
+            params.push(Node::TypedIdentifier {
+                name: ident.val,
+                span: ident.span.to(typ.span()),
+                typ: typ.into(),
+                symbol: None,
+            });
+
+            if matches!(self.at()?.typ, Comma) {
+                self.consume()?;
+            } else {
+                break;
+            }
+        }
+        self.expect(CloseParen, "to close the parameter list")?;
+
+        let mut return_type = None;
+        if matches!(self.at()?.typ, Arrow) {
+            self.consume()?;
+            return_type = Some(self.parse_type()?.into());
+        }
+
+        // A loop around the function definition does not make `break` valid inside of it.
+        let loops = std::mem::take(&mut self.loops);
+        let match_depth = std::mem::take(&mut self.match_depth);
+        let body = self.parse_block();
+        self.loops = loops;
+        self.match_depth = match_depth;
+        let (body, body_span) = body?;
+
+        Ok(Node::Function {
+            name,
+            type_params,
+            type_args: vec![],
+            params,
+            return_type,
+            body,
+            span: node.span.to(body_span),
+        })
+    }
+
+    fn parse_struct(&mut self) -> Result<Node> {
+        use TokeType::*;
+
+        let node = self.consume()?;
+        let name = self.expect(Identifier, "after `struct`")?.val;
+
+        self.expect(OpenBrace, "after struct name")?;
+        let mut fields = vec![];
+        while !self.eof() && matches!(self.at()?.typ, Identifier) {
+            let ident = self.consume()?;
+            self.expect(Colon, "after field name")?;
+
+            let typ = self.parse_type()?;
+            fields.push(Node::TypedIdentifier {
+                name: ident.val,
+                span: ident.span.to(typ.span()),
+                typ: typ.into(),
+                symbol: None,
+            });
+
+            if matches!(self.at()?.typ, Comma) {
+                self.consume()?;
+            } else {
+                break;
+            }
+        }
+        let close = self.expect(CloseBrace, "to close the struct")?;
+
+        Ok(Node::Struct {
+            name,
+            fields,
+            span: node.span.to(close.span),
+        })
+    }
+
+    fn parse_enum(&mut self) -> Result<Node> {
+        use TokeType::*;
+
+        let node = self.consume()?;
+        let name = self.expect(Identifier, "after `enum`")?.val;
+
+        self.expect(OpenBrace, "after enum name")?;
+        let mut variants = vec![];
+        while !self.eof() && matches!(self.at()?.typ, Identifier) {
+            let ident = self.consume()?;
+
+            let mut fields = vec![];
+            let mut span = ident.span;
+            if matches!(self.at()?.typ, OpenParen) {
+                self.consume()?;
+                while !self.eof() && !matches!(self.at()?.typ, CloseParen) {
+                    fields.push(self.parse_type()?);
+                    if matches!(self.at()?.typ, Comma) {
+                        self.consume()?;
+                    } else {
+                        break;
+                    }
+                }
+                span = span.to(self.expect(CloseParen, "to close the variant fields")?.span);
+            }
+            variants.push(Node::Variant {
+                name: ident.val,
+                fields,
+                span,
+            });
+
+            if matches!(self.at()?.typ, Comma) {
+                self.consume()?;
+            } else {
+                break;
+            }
+        }
+        let close = self.expect(CloseBrace, "to close the enum")?;
+
+        Ok(Node::Enum {
+            name,
+            variants,
+            span: node.span.to(close.span),
+        })
+    }
+
+    fn parse_match(&mut self, node: Token) -> Result<Node> {
+        use TokeType::*;
+
+        let scrutinee = self.parse_condition()?;
+        self.expect(OpenBrace, "after the matched value")?;
+
+        let mut arms = vec![];
+        while !self.eof() && !matches!(self.at()?.typ, CloseBrace) {
+            let pattern = self.parse_pattern()?;
+            self.expect(FatArrow, "after the pattern")?;
+
+            self.match_depth += 1;
+            let body = if matches!(self.at()?.typ, OpenBrace) {
+                self.parse_block()
+                    .map(|(body, span)| Node::Block { body, span })
+            } else {
+                self.parse_delimited_expr()
+            };
+            self.match_depth -= 1;
+            let body = body?;
+
+            let is_block = matches!(body, Node::Block { .. });
+            arms.push(Node::MatchArm {
+                span: pattern.span().to(body.span()),
+                pattern: pattern.into(),
+                body: body.into(),
+            });
+
+            // The comma is optional after a block, like in Rust.
+            if matches!(self.at()?.typ, Comma) {
+                self.consume()?;
+            } else if !is_block {
+                break;
+            }
+        }
+        let close = self.expect(CloseBrace, "to close the match")?;
+
+        Ok(Node::Match {
+            scrutinee: scrutinee.into(),
+            arms,
+            scrutinee_type: None,
+            resolved_type: None,
+            span: node.span.to(close.span),
+        })
+    }
+
+    /// `_`, or `Enum::Variant` with optional bindings for its fields.
+    fn parse_pattern(&mut self) -> Result<Node> {
+        use TokeType::*;
+
+        let name = self.expect(Identifier, "as the pattern")?;
+        if name.val == "_" {
+            return Ok(Node::Wildcard { span: name.span });
+        }
+
+        self.expect(DoubleColon, "after the enum name")?;
+        let variant = self.expect(Identifier, "as the variant name")?;
+
+        let mut bindings = vec![];
+        let mut span = name.span.to(variant.span);
+        if !self.eof() && matches!(self.at()?.typ, OpenParen) {
+            self.consume()?;
+            while !self.eof() && !matches!(self.at()?.typ, CloseParen) {
+                let binding = self.expect(Identifier, "as the name of the field")?;
+                bindings.push(Node::Identifier {
+                    name: binding.val,
+                    symbol: None,
+                    span: binding.span,
+                });
+                if matches!(self.at()?.typ, Comma) {
+                    self.consume()?;
+                } else {
+                    break;
+                }
+            }
+            span = span.to(self.expect(CloseParen, "to close the pattern")?.span);
+        }
+
+        Ok(Node::VariantPattern {
+            name: name.val,
+            variant: variant.val,
+            bindings,
+            binding_types: vec![],
+            span,
+        })
+    }
+
+    fn parse_if(&mut self) -> Result<Node> {
+        let node = self.consume()?;
+        let condition = self.parse_condition()?;
+        let (then_body, then_span) = self.parse_block()?;
+        let mut span = node.span.to(then_span);
+
+        let mut else_body = None;
+        if let Some(tok) = self.tokens.first() {
+            if tok.typ == TokeType::Keyword && tok.val == "else" {
+                self.consume()?;
+
+                let is_else_if = self
+                    .tokens
+                    .first()
+                    .is_some_and(|tok| tok.typ == TokeType::Keyword && tok.val == "if");
+
+                if is_else_if {
+                    let else_if = self.parse_if()?;
+                    span = span.to(else_if.span());
+                    else_body = Some(vec![else_if]);
+                } else {
+                    let (body, body_span) = self.parse_block()?;
+                    span = span.to(body_span);
+                    else_body = Some(body);
+                }
+            }
+        }
+
+        Ok(Node::If {
+            condition: condition.into(),
+            then_body,
+            else_body,
+            span,
+        })
+    }
+
+    fn parse_while(&mut self) -> Result<Node> {
+        let node = self.consume()?;
+        let condition = self.parse_condition()?;
+        let (body, body_span) = self.parse_loop_body(node.span)?;
+
+        Ok(Node::While {
+            condition: condition.into(),
+            body,
+            span: node.span.to(body_span),
+        })
+    }
+
+    fn parse_for(&mut self) -> Result<Node> {
+        let node = self.consume()?;
+        let var = self.expect(TokeType::Identifier, "after `for`")?;
+
+        self.expect_keyword("in", "after the loop variable")?;
+
+        let start = self.parse_condition()?;
+        // Anything but a range has to be a `Vec`, which the type checker makes sure of.
+        let iter = match self.tokens.first() {
+            Some(tok) if tok.typ == TokeType::Operator && tok.val == "Range" => {
+                self.consume()?;
+                let end = self.parse_condition()?;
+                Node::Range {
+                    span: start.span().to(end.span()),
+                    start: start.into(),
+                    end: end.into(),
+                }
+            }
+            _ => start,
+        };
+
+        let (body, body_span) = self.parse_loop_body(node.span)?;
+
+        Ok(Node::For {
+            var: var.val,
+            iter: iter.into(),
+            symbol: None,
+            iter_type: None,
+            body,
+            span: node.span.to(body_span),
+        })
+    }
+
+    fn parse_return(&mut self) -> Result<Node> {
+        let node = self.consume()?;
+
+        // Without semicolons, the value is omitted when the block, the statement or the line
+        // ends right away.
+        let line = self.line;
+        let has_value = self.tokens.first().is_some_and(|tok| match tok.typ {
+            _ if tok.span.line != line => false,
+            TokeType::CloseBrace => false,
+            TokeType::Keyword => !STATEMENT_KEYWORDS.contains(&tok.val.as_str()),
+            _ => true,
+        });
+
+        if !has_value {
+            return Ok(Node::Return {
+                value: None,
+                span: node.span,
+            });
+        }
+
+        let value = self.parse_expr()?;
+        Ok(Node::Return {
+            span: node.span.to(value.span()),
+            value: Some(value.into()),
+        })
+    }
+
+    /// Parses the body of the loop that starts at `start`.
+    fn parse_loop_body(&mut self, start: Span) -> Result<(Vec<Node>, Span)> {
+        self.loops.push(start);
+        let match_depth = std::mem::take(&mut self.match_depth);
+        let body = self.parse_block();
+        self.loops.pop();
+        self.match_depth = match_depth;
+        body
+    }
+
+    fn parse_loop_control(&mut self) -> Result<Node> {
+        let node = self.consume()?;
+
+        let Some(&loop_span) = self.loops.last() else {
+            return Err(Diagnostic::error(
+                "E0107",
+                format!("`{}` outside of a loop", node.val),
+                node.span,
+            )
+            .with_label(format!("cannot `{}` outside of a loop", node.val))
+            .into());
+        };
+
+        Ok(match node.val.as_str() {
+            "break" => Node::Break {
+                loop_span: (self.match_depth > 0).then_some(loop_span),
+                span: node.span,
+            },
+            _ => Node::Continue { span: node.span },
+        })
+    }
+
+    /// Parses `{ statements }`, recovering from errors inside the block.
+    fn parse_block(&mut self) -> Result<(Vec<Node>, Span)> {
+        let open = self.expect(TokeType::OpenBrace, "to start a block")?;
+
+        let mut body = vec![];
+        while !self.eof() && !matches!(self.at()?.typ, TokeType::CloseBrace) {
+            if let Some(stmt) = self.parse_stmt_or_recover() {
+                body.push(stmt);
+            }
+        }
+        let close = self.expect(TokeType::CloseBrace, "to close the block")?;
+
+        Ok((body, open.span.to(close.span)))
+    }
+
+    fn parse_type(&mut self) -> Result<Node> {
+        use TokeType::*;
+
+        if !self.eof() && matches!(self.at()?.typ, OpenBracket) {
+            let open = self.consume()?;
+            let element = self.parse_type()?;
+            self.expect(Semicolon, "after the array element type")?;
+
+            let len = self.expect(Int, "as the array length")?;
+            // The lexer normalizes integers to decimal, a suffix may follow the digits.
+            let digits = len.val.split(|c: char| !c.is_ascii_digit()).next().unwrap();
+            let Ok(len) = digits.parse::<usize>() else {
+                return Err(
+                    Diagnostic::error("E0109", "array length is too large", len.span).into(),
+                );
+            };
+
+            let close = self.expect(CloseBracket, "to close the array type")?;
+            return Ok(Node::ArrayType {
+                element: element.into(),
+                len,
+                span: open.span.to(close.span),
+            });
+        }
+
+        let ident = self.expect(Identifier, "as the type name")?;
+
+        if !self.eof() && self.at()?.typ == Operator && self.at()?.val == "<" {
+            self.consume()?;
+            let mut args = vec![self.parse_type()?];
+            while !self.eof() && matches!(self.at()?.typ, Comma) {
+                self.consume()?;
+                args.push(self.parse_type()?);
+            }
+
+            // In `let v: Vec<int>= []` the lexer sees `>=`, which also ends the arguments.
+            if !self.eof() && self.at()?.typ == Operator && self.at()?.val == ">=" {
+                let tok = &mut self.tokens[0];
+                tok.typ = Assignment;
+                tok.val = "=".into();
+                return Ok(Node::GenericType {
+                    name: ident.val,
+                    args,
+                    span: ident.span.to(tok.span),
+                });
+            }
+            let close = self.expect_operator(">", "to close the type arguments")?;
+
+            return Ok(Node::GenericType {
+                name: ident.val,
+                args,
+                span: ident.span.to(close.span),
+            });
+        }
+
+        Ok(Node::Identifier {
+            name: ident.val,
+            symbol: None,
+            span: ident.span,
+        })
+    }
+
+    /// The arguments of a call, after the opening parenthesis; returns the closing one too.
+    fn parse_args(&mut self) -> Result<(Vec<Node>, Token)> {
+        use TokeType::*;
+
+        let mut args = vec![];
+        while !self.eof() && !matches!(self.at()?.typ, CloseParen) {
+            args.push(self.parse_delimited_expr()?);
+            if matches!(self.at()?.typ, Comma) {
+                self.consume()?;
+            } else {
+                break;
+            }
+        }
+        let close = self.expect(CloseParen, "to close the argument list")?;
+
+        Ok((args, close))
     }
 
     fn parse_primary_expr(&mut self) -> Result<Node> {
@@ -75,107 +771,263 @@ impl Parser {
 
         match &node.typ {
             Identifier => Ok(if self.at().is_ok() {
-                if matches!(self.at()?.typ, Assignment) {
-                    let name = node.val;
-                    let op = self.expect(Assignment)?;
+                if self.at_struct_literal() {
+                    return self.parse_struct_literal(node);
+                } else if matches!(self.at()?.typ, DoubleColon) {
+                    self.consume()?;
+                    let variant = self.expect(Identifier, "after `::`")?;
 
-                    let val = self.parse_additive_expr()?;
-                    Node::BinaryExpr {
-                        left: Box::new(Node::Variable { name }),
-                        right: Box::new(val),
-                        operator: op.val.into(),
+                    let (args, end) = if !self.eof() && matches!(self.at()?.typ, OpenParen) {
+                        self.consume()?;
+                        let (args, close) = self.parse_args()?;
+                        (args, close.span)
+                    } else {
+                        (vec![], variant.span)
+                    };
+                    Node::EnumLiteral {
+                        name: node.val,
+                        variant: variant.val,
+                        args,
+                        span: node.span.to(end),
                     }
                 } else if matches!(self.at()?.typ, OpenParen) {
                     let name = node.val;
-                    self.expect(OpenParen)?;
-                    let mut args = vec![];
-                    while !self.eof() && !matches!(self.at()?.typ, CloseParen) {
-                        args.push(self.parse_additive_expr()?);
-                        if matches!(self.at()?.typ, Comma) {
-                            self.consume()?;
-                        }
-                    }
-                    self.expect(CloseParen)?;
+                    self.consume()?;
+                    let (args, close) = self.parse_args()?;
                     Node::CallExpr {
-                        callee: Box::new(Node::Identifier { name }),
+                        callee: Box::new(Node::Identifier {
+                            name,
+                            symbol: None,
+                            span: node.span,
+                        }),
                         args,
+                        type_args: vec![],
+                        span: node.span.to(close.span),
                     }
                 } else {
                     Node::Identifier {
-                        name: node.val.into(),
+                        name: node.val,
+                        symbol: None,
+                        span: node.span,
                     }
                 }
             } else {
                 Node::Identifier {
-                    name: node.val.into(),
+                    name: node.val,
+                    symbol: None,
+                    span: node.span,
                 }
             }),
-            Int => Ok(Node::NumericLiteral {
-                typ: "int".into(),
-                val: node.val.into(),
-            }),
-            Float => Ok(Node::NumericLiteral {
-                typ: "float".into(),
-                val: node.val.into(),
+            Int | Float => {
+                // The lexer leaves the suffix at the end of the value, as in `255u8` or `1.5f32`.
+                let is_float = node.typ == Float;
+                let (val, typ) = match node.val.find(|c: char| {
+                    if is_float {
+                        c == 'f'
+                    } else {
+                        c.is_ascii_alphabetic()
+                    }
+                }) {
+                    Some(suffix) => (&node.val[..suffix], &node.val[suffix..]),
+                    None => (node.val.as_str(), if is_float { "float" } else { "int" }),
+                };
+
+                Ok(Node::NumericLiteral {
+                    typ: typ.into(),
+                    val: val.into(),
+                    span: node.span,
+                })
+            }
+            String => Ok(Node::StringLiteral {
+                val: node.val,
+                span: node.span,
             }),
+            OpenBracket => {
+                let mut elements = vec![];
+                while !self.eof() && !matches!(self.at()?.typ, CloseBracket) {
+                    elements.push(self.parse_delimited_expr()?);
+                    if matches!(self.at()?.typ, Comma) {
+                        self.consume()?;
+                    } else {
+                        break;
+                    }
+                }
+                let close = self.expect(CloseBracket, "to close the array literal")?;
+
+                Ok(Node::ArrayLiteral {
+                    elements,
+                    resolved_type: None,
+                    span: node.span.to(close.span),
+                })
+            }
             OpenParen => {
-                let val = self.parse_additive_expr();
-                self.expect(CloseParen)?;
+                let val = self.parse_delimited_expr();
+                self.expect(CloseParen, "to close the parenthesized expression")?;
                 val
             }
+            Keyword if node.val == "match" => self.parse_match(node),
+            Keyword if node.val == "true" || node.val == "false" => Ok(Node::BoolLiteral {
+                val: node.val == "true",
+                span: node.span,
+            }),
             Keyword => {
-                let name = node.val;
-                match name.as_str() {
-                    "const" | "let" => {
-                        let name = self.expect(Identifier)?.val;
-                        let op = self.expect(Assignment)?;
-                        assert!(op.val == "=", "Only '=' is allowed when declaring a variable, what were you thinking?");
-
-                        let val = self.parse_additive_expr()?;
-                        Ok(Node::BinaryExpr {
-                            left: Box::new(Node::Variable { name }),
-                            right: Box::new(val),
-                            operator: op.val.into(),
-                        })
-                    }
-                    "fn" => {
-                        let name = self.expect(Identifier)?.val;
-
-                        self.expect(OpenParen)?;
-                        let mut params = vec![];
-                        while !self.eof() && matches!(self.at()?.typ, Identifier) {
-                            let ident = self.expect(Identifier)?;
-                            self.expect(Colon)?;
-
-                            params.push(Node::TypedIdentifier {
-                                name: ident.val.into(),
-                                typ: self.parse_primary_expr()?.into(),
-                            });
-
-                            if matches!(self.at()?.typ, Comma) {
-                                self.consume()?;
-                            }
-                        }
-                        self.expect(CloseParen)?;
-                        self.expect(OpenBrace)?;
+                // Leave the token in place so that error recovery can synchronize on it.
+                self.tokens.insert(0, node.clone());
+                Err(Diagnostic::error(
+                    "E0102",
+                    format!("unexpected keyword `{}`", node.val),
+                    node.span,
+                )
+                .with_label("expected an expression")
+                .into())
+            }
+            _ => {
+                self.tokens.insert(0, node.clone());
+                Err(Diagnostic::error(
+                    "E0101",
+                    format!("unexpected token `{}`", node.val),
+                    node.span,
+                )
+                .with_label("expected an expression")
+                .into())
+            }
+        }
+    }
 
-                        let mut body = vec![];
-                        while !self.eof() && !matches!(self.at()?.typ, CloseBrace) {
-                            body.push(self.parse_additive_expr()?);
-                        }
-                        self.expect(CloseBrace)?;
+    /// Whether the tokens after a name start a struct literal: `{` followed by `}` or `field:`.
+    fn at_struct_literal(&self) -> bool {
+        use TokeType::*;
 
-                        Ok(Node::Function { name, params, body })
-                    }
-                    _ => bail!("Unexpected keyword: {}", name),
+        !self.no_struct_literal
+            && match self.tokens.as_slice() {
+                [open, close, ..] if open.typ == OpenBrace && close.typ == CloseBrace => true,
+                [open, field, colon, ..] => {
+                    open.typ == OpenBrace && field.typ == Identifier && colon.typ == Colon
                 }
+                _ => false,
+            }
+    }
+
+    fn parse_struct_literal(&mut self, name: Token) -> Result<Node> {
+        use TokeType::*;
+
+        self.consume()?;
+        let mut fields = vec![];
+        while !self.eof() && matches!(self.at()?.typ, Identifier) {
+            let ident = self.consume()?;
+            self.expect(Colon, "after field name")?;
+
+            let value = self.parse_delimited_expr()?;
+            fields.push(Node::FieldInit {
+                name: ident.val,
+                span: ident.span.to(value.span()),
+                value: value.into(),
+            });
+
+            if matches!(self.at()?.typ, Comma) {
+                self.consume()?;
+            } else {
+                break;
             }
+        }
+        let close = self.expect(CloseBrace, "to close the struct literal")?;
+
+        Ok(Node::StructLiteral {
+            name: name.val,
+            fields,
+            span: name.span.to(close.span),
+        })
+    }
+
+    /// Assignments bind loosest of all and group to the right; they are only valid
+    /// on variables and fields.
+    fn parse_expr(&mut self) -> Result<Node> {
+        let target = self.parse_binary_expr(0)?;
+        if self.eof() || !matches!(self.at()?.typ, TokeType::Assignment) {
+            return Ok(target);
+        }
+
+        let op = self.consume()?;
+        let target = match target {
+            Node::Identifier { name, symbol, span } => Node::Variable { name, symbol, span },
+            Node::MemberExpr { .. } => target,
             _ => {
-                bail!("Unexpected token: {:?}", node);
+                return Err(Diagnostic::error(
+                    "E0108",
+                    "invalid left-hand side of assignment",
+                    target.span(),
+                )
+                .with_label("cannot assign to this expression")
+                .into())
             }
+        };
+        let value = self.parse_expr()?;
+
+        Ok(Node::BinaryExpr {
+            span: target.span().to(value.span()),
+            left: target.into(),
+            right: value.into(),
+            operator: op.val,
+            resolved_type: None,
+        })
+    }
+
+    /// Parses an expression that is followed by a block.
+    fn parse_condition(&mut self) -> Result<Node> {
+        let no_struct_literal = std::mem::replace(&mut self.no_struct_literal, true);
+        let expr = self.parse_expr();
+        self.no_struct_literal = no_struct_literal;
+        expr
+    }
+
+    /// Parses an expression between delimiters, where struct literals are unambiguous again.
+    fn parse_delimited_expr(&mut self) -> Result<Node> {
+        let no_struct_literal = std::mem::replace(&mut self.no_struct_literal, false);
+        let expr = self.parse_expr();
+        self.no_struct_literal = no_struct_literal;
+        expr
+    }
+
+    /// Binding power of the operators that sit above the arithmetic ones;
+    /// higher binds tighter.
+    fn binary_precedence(op: &str) -> Option<u8> {
+        match op {
+            "||" => Some(1),
+            "&&" => Some(2),
+            "==" | "!=" => Some(3),
+            "<" | ">" | "<=" | ">=" => Some(4),
+            _ => None,
         }
     }
 
+    /// Precedence climbing over the comparison and logical operators,
+    /// all of which are left associative.
+    fn parse_binary_expr(&mut self, min_precedence: u8) -> Result<Node> {
+        let mut left = self.parse_additive_expr()?;
+
+        while let Some(tok) = self.tokens.first() {
+            let Some(precedence) = Self::binary_precedence(&tok.val) else {
+                break;
+            };
+            if tok.typ != TokeType::Operator || precedence < min_precedence {
+                break;
+            }
+
+            let op = self.consume()?.val;
+            let right = self.parse_binary_expr(precedence + 1)?;
+
+            left = Node::BinaryExpr {
+                span: left.span().to(right.span()),
+                left: left.into(),
+                right: right.into(),
+                operator: op,
+                resolved_type: None,
+            };
+        }
+
+        Ok(left)
+    }
+
     fn parse_additive_expr(&mut self) -> Result<Node> {
         let mut left = self.parse_multiplicative_expr()?;
 
@@ -183,13 +1035,15 @@ impl Parser {
             && matches!(self.at()?.typ, TokeType::Operator)
             && ["+", "-"].contains(&self.at()?.val.as_str())
         {
-            let op = &self.expect(TokeType::Operator)?.val;
+            let op = &self.consume()?.val;
             let right = self.parse_multiplicative_expr()?;
 
             left = Node::BinaryExpr {
+                span: left.span().to(right.span()),
                 left: left.clone().into(),
                 right: right.into(),
                 operator: op.into(),
+                resolved_type: None,
             };
         }
 
@@ -197,22 +1051,118 @@ impl Parser {
     }
 
     fn parse_multiplicative_expr(&mut self) -> Result<Node> {
-        let mut left = self.parse_primary_expr()?;
+        let mut left = self.parse_cast_expr()?;
 
         while !self.eof()
             && matches!(self.at()?.typ, TokeType::Operator)
             && ["*", "/", "%"].contains(&self.at()?.val.as_str())
         {
-            let op = &self.expect(TokeType::Operator)?.val;
-            let right = self.parse_primary_expr()?;
+            let op = &self.consume()?.val;
+            let right = self.parse_cast_expr()?;
 
             left = Node::BinaryExpr {
+                span: left.span().to(right.span()),
                 left: left.clone().into(),
                 right: right.into(),
                 operator: op.into(),
+                resolved_type: None,
             };
         }
 
         Ok(left)
     }
+
+    /// `as` binds tighter than the arithmetic operators, but looser than the unary ones,
+    /// so `-x as u8` casts the negated value.
+    fn parse_cast_expr(&mut self) -> Result<Node> {
+        let mut expr = self.parse_unary_expr()?;
+
+        while !self.eof() && matches!(self.at()?.typ, TokeType::Keyword) && self.at()?.val == "as" {
+            self.consume()?;
+            let typ = self.parse_type()?;
+
+            expr = Node::Cast {
+                span: expr.span().to(typ.span()),
+                expr: expr.into(),
+                typ: typ.into(),
+            };
+        }
+
+        Ok(expr)
+    }
+
+    fn parse_unary_expr(&mut self) -> Result<Node> {
+        if !self.eof()
+            && matches!(self.at()?.typ, TokeType::Operator)
+            && ["!", "-"].contains(&self.at()?.val.as_str())
+        {
+            let op = self.consume()?;
+            let operand = self.parse_unary_expr()?;
+
+            return Ok(Node::UnaryExpr {
+                span: op.span.to(operand.span()),
+                operand: operand.into(),
+                operator: op.val,
+                resolved_type: None,
+            });
+        }
+
+        self.parse_postfix_expr()
+    }
+
+    fn parse_postfix_expr(&mut self) -> Result<Node> {
+        let mut object = self.parse_primary_expr()?;
+
+        loop {
+            if self.eof() {
+                break;
+            }
+
+            if self.at()?.typ == TokeType::Operator && self.at()?.val == "." {
+                self.consume()?;
+                let property = self.expect(TokeType::Identifier, "after `.`")?;
+
+                object = Node::MemberExpr {
+                    span: object.span().to(property.span),
+                    object: object.into(),
+                    property: Node::Identifier {
+                        name: property.val,
+                        symbol: None,
+                        span: property.span,
+                    }
+                    .into(),
+                    computed: false,
+                    object_type: None,
+                };
+
+                // A method call, such as `list.push(1)`.
+                if !self.eof() && matches!(self.at()?.typ, TokeType::OpenParen) {
+                    self.consume()?;
+                    let (args, close) = self.parse_args()?;
+                    object = Node::CallExpr {
+                        span: object.span().to(close.span),
+                        callee: object.into(),
+                        args,
+                        type_args: vec![],
+                    };
+                }
+            } else if self.at()?.typ == TokeType::OpenBracket {
+                self.consume()?;
+                let index = self.parse_delimited_expr()?;
+                let close = self.expect(TokeType::CloseBracket, "to close the index")?;
+
+                object = Node::MemberExpr {
+                    span: object.span().to(close.span),
+                    object: object.into(),
+                    property: index.into(),
+                    computed: true,
+                    object_type: None,
+                };
+            } else {
+                break;
+            }
+        }
+
+        Ok(object)
+    }
 }
@@ -0,0 +1,512 @@
+use crate::diagnostics::Diagnostic;
+use crate::runtime;
+use crate::types::{Node, Span};
+use std::collections::HashMap;
+
+pub type SymbolId = usize;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SymbolKind {
+    /// A function provided by the runtime, such as `print`.
+    Builtin,
+    Function,
+    Parameter,
+    Variable,
+    LoopVariable,
+    /// A name bound by a `match` pattern.
+    Binding,
+}
+
+impl SymbolKind {
+    pub fn is_function(self) -> bool {
+        matches!(self, SymbolKind::Builtin | SymbolKind::Function)
+    }
+}
+
+/// Something a name can refer to; identifiers point into the symbol table by index.
+#[derive(Debug, Clone)]
+pub struct Symbol {
+    pub kind: SymbolKind,
+    pub mutable: bool,
+    pub span: Span,
+}
+
+#[derive(Default)]
+struct Scope {
+    names: HashMap<String, SymbolId>,
+    /// Variables declared further down in this block, to tell "used before declaration"
+    /// apart from "not declared at all".
+    pending: HashMap<String, Span>,
+}
+
+struct Resolver {
+    symbols: Vec<Symbol>,
+    scopes: Vec<Scope>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+/// Resolves every name in the program to the symbol it refers to, filling in the
+/// `symbol` of identifiers, variables and declarations.
+///
+/// Symbols are numbered in the order they are declared; the type checker keys the types of
+/// variables by these ids, so that shadowed names never get mixed up.
+pub fn resolve(program: &mut Node) -> Vec<Diagnostic> {
+    let mut resolver = Resolver {
+        symbols: vec![],
+        scopes: vec![],
+        diagnostics: vec![],
+    };
+
+    resolver.resolve_program(program);
+    resolver.diagnostics
+}
+
+fn describe(span: Span) -> String {
+    format!("line {}, column {}", span.line, span.col)
+}
+
+impl Resolver {
+    fn lookup(&self, name: &str) -> Option<SymbolId> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.names.get(name).copied())
+    }
+
+    fn declare(&mut self, name: &str, kind: SymbolKind, mutable: bool, span: Span) -> SymbolId {
+        let id = self.symbols.len();
+        self.symbols.push(Symbol {
+            kind,
+            mutable,
+            span,
+        });
+
+        let scope = self.scopes.last().expect("there is always a global scope");
+        if let Some(&previous) = scope.names.get(name) {
+            self.diagnostics.push(
+                Diagnostic::error(
+                    "E0204",
+                    format!("`{}` is already declared in this scope", name),
+                    span,
+                )
+                .with_note(format!(
+                    "the previous declaration is at {}",
+                    describe(self.symbols[previous].span)
+                )),
+            );
+        } else if let Some(previous) = self.lookup(name) {
+            let previous = &self.symbols[previous];
+            let note = match previous.kind {
+                SymbolKind::Builtin => format!("`{}` is a builtin function", name),
+                _ => format!("the shadowed declaration is at {}", describe(previous.span)),
+            };
+            self.diagnostics.push(
+                Diagnostic::warning(
+                    "W0201",
+                    format!("`{}` shadows an outer declaration", name),
+                    span,
+                )
+                .with_note(note),
+            );
+        }
+
+        self.scopes
+            .last_mut()
+            .unwrap()
+            .names
+            .insert(name.into(), id);
+        id
+    }
+
+    fn unresolved(&mut self, name: &str, span: Span, what: &str) {
+        let declared_later = self
+            .scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.pending.get(name).copied());
+
+        self.diagnostics.push(match declared_later {
+            Some(declaration) => Diagnostic::error(
+                "E0203",
+                format!("cannot use `{}` before its declaration", name),
+                span,
+            )
+            .with_note(format!(
+                "`{}` is declared at {}",
+                name,
+                describe(declaration)
+            )),
+            None => Diagnostic::error(
+                if what == "function" { "E0202" } else { "E0201" },
+                format!("cannot find {} `{}` in this scope", what, name),
+                span,
+            ),
+        });
+    }
+
+    /// Fields and elements can only be assigned through a mutable binding, a `const` struct
+    /// or array is constant all the way down.
+    fn check_field_assignment(&mut self, target: &Node) {
+        let what = match target {
+            Node::MemberExpr { computed: true, .. } => "assign to an element of",
+            _ => "assign to a field of",
+        };
+        self.check_mutable_root(target, what, target.span());
+    }
+
+    /// Reports `what` when the root of `target` is a constant, or a temporary value that is
+    /// not stored anywhere, such as the result of a call.
+    fn check_mutable_root(&mut self, target: &Node, what: &str, span: Span) {
+        let mut root = target;
+        while let Node::MemberExpr { object, .. } = root {
+            root = object;
+        }
+
+        let Node::Identifier { name, symbol, .. } = root else {
+            self.diagnostics.push(
+                Diagnostic::error("E0205", format!("cannot {} a temporary value", what), span)
+                    .with_help("store the value in a `let` binding first"),
+            );
+            return;
+        };
+        // Unknown names are reported already.
+        let Some(id) = symbol else {
+            return;
+        };
+        let symbol = &self.symbols[*id];
+        if !symbol.mutable && !symbol.kind.is_function() {
+            self.diagnostics.push(
+                Diagnostic::error(
+                    "E0205",
+                    format!("cannot {} constant `{}`", what, name),
+                    span,
+                )
+                .with_note(format!(
+                    "`{}` is declared at {}",
+                    name,
+                    describe(symbol.span)
+                ))
+                .with_help("declare it with `let` to make it mutable"),
+            );
+        }
+    }
+
+    fn resolve_program(&mut self, program: &mut Node) {
+        let Node::Program { body, .. } = program else {
+            unreachable!()
+        };
+
+        self.scopes.push(Scope::default());
+        for builtin in runtime::BUILTINS {
+            self.declare(builtin, SymbolKind::Builtin, false, Span::default());
+        }
+
+        // Functions are visible everywhere, including before their definition.
+        for stmt in body.iter() {
+            if let Node::Function { name, span, .. } = stmt {
+                if runtime::BUILTINS.contains(&name.as_str()) {
+                    self.diagnostics.push(Diagnostic::error(
+                        "E0209",
+                        format!("`{}` is a builtin function and cannot be redefined", name),
+                        *span,
+                    ));
+                } else {
+                    self.declare(name, SymbolKind::Function, false, *span);
+                }
+            }
+        }
+
+        for stmt in body {
+            match stmt {
+                Node::Function { params, body, .. } => {
+                    self.scopes.push(Scope::default());
+                    for param in params {
+                        if let Node::TypedIdentifier {
+                            name, symbol, span, ..
+                        } = param
+                        {
+                            *symbol = Some(self.declare(name, SymbolKind::Parameter, true, *span));
+                        }
+                    }
+                    self.resolve_block(body);
+                    self.scopes.pop();
+                }
+                _ => self.resolve_stmt(stmt),
+            }
+        }
+        self.scopes.pop();
+    }
+
+    fn resolve_block(&mut self, body: &mut [Node]) {
+        // Reversed, so that the first declaration of a name is the one that is kept.
+        let pending = body
+            .iter()
+            .rev()
+            .filter_map(|stmt| match stmt {
+                Node::VarDecl { name, span, .. } => Some((name.clone(), *span)),
+                _ => None,
+            })
+            .collect();
+
+        self.scopes.push(Scope {
+            names: HashMap::new(),
+            pending,
+        });
+        for stmt in body {
+            self.resolve_stmt(stmt);
+        }
+        self.scopes.pop();
+    }
+
+    fn resolve_stmt(&mut self, stmt: &mut Node) {
+        match stmt {
+            Node::VarDecl {
+                name,
+                mutable,
+                init,
+                symbol,
+                span,
+                ..
+            } => {
+                // The initializer is resolved first, so `let x = x + 1` refers to an outer `x`.
+                self.resolve_expr(init);
+
+                self.scopes
+                    .last_mut()
+                    .unwrap()
+                    .pending
+                    .remove(name.as_str());
+                *symbol = Some(self.declare(name, SymbolKind::Variable, *mutable, *span));
+            }
+            Node::If {
+                condition,
+                then_body,
+                else_body,
+                ..
+            } => {
+                self.resolve_expr(condition);
+                self.resolve_block(then_body);
+                if let Some(else_body) = else_body {
+                    self.resolve_block(else_body);
+                }
+            }
+            Node::While {
+                condition, body, ..
+            } => {
+                self.resolve_expr(condition);
+                self.resolve_block(body);
+            }
+            Node::For {
+                var,
+                iter,
+                body,
+                symbol,
+                span,
+                ..
+            } => {
+                self.resolve_expr(iter);
+
+                self.scopes.push(Scope::default());
+                *symbol = Some(self.declare(var, SymbolKind::LoopVariable, false, *span));
+                self.resolve_block(body);
+                self.scopes.pop();
+            }
+            Node::Return { value, .. } => {
+                if let Some(value) = value {
+                    self.resolve_expr(value);
+                }
+            }
+            // Nested functions and types are rejected by the type checker, which also
+            // resolves the names of types.
+            Node::Break { .. }
+            | Node::Continue { .. }
+            | Node::Function { .. }
+            | Node::Struct { .. }
+            | Node::Enum { .. } => {}
+            _ => self.resolve_expr(stmt),
+        }
+    }
+
+    fn resolve_expr(&mut self, expr: &mut Node) {
+        match expr {
+            Node::Identifier { name, symbol, span } => {
+                let Some(id) = self.lookup(name) else {
+                    return self.unresolved(name, *span, "value");
+                };
+
+                if self.symbols[id].kind.is_function() {
+                    self.diagnostics.push(
+                        Diagnostic::error(
+                            "E0208",
+                            format!("expected a value, found function `{}`", name),
+                            *span,
+                        )
+                        .with_help(format!("call the function with `{}(...)`", name)),
+                    );
+                }
+                *symbol = Some(id);
+            }
+            Node::Variable { name, symbol, span } => {
+                let Some(id) = self.lookup(name) else {
+                    return self.unresolved(name, *span, "value");
+                };
+
+                let target = &self.symbols[id];
+                if target.kind.is_function() {
+                    self.diagnostics.push(Diagnostic::error(
+                        "E0205",
+                        format!("cannot assign to function `{}`", name),
+                        *span,
+                    ));
+                } else if target.kind == SymbolKind::Binding {
+                    self.diagnostics.push(
+                        Diagnostic::error(
+                            "E0205",
+                            format!("cannot assign to pattern binding `{}`", name),
+                            *span,
+                        )
+                        .with_help("copy it into a `let` binding inside the arm instead"),
+                    );
+                } else if target.kind == SymbolKind::LoopVariable {
+                    self.diagnostics.push(
+                        Diagnostic::error(
+                            "E0205",
+                            format!("cannot assign to loop variable `{}`", name),
+                            *span,
+                        )
+                        .with_help("copy it into a `let` binding inside the loop instead"),
+                    );
+                } else if !target.mutable {
+                    self.diagnostics.push(
+                        Diagnostic::error(
+                            "E0205",
+                            format!("cannot assign to constant `{}`", name),
+                            *span,
+                        )
+                        .with_note(format!(
+                            "`{}` is declared at {}",
+                            name,
+                            describe(target.span)
+                        ))
+                        .with_help("declare it with `let` to make it mutable"),
+                    );
+                }
+                *symbol = Some(id);
+            }
+            Node::CallExpr { callee, args, .. } => {
+                if let Node::Identifier { name, symbol, span } = callee.as_mut() {
+                    match self.lookup(name) {
+                        Some(id) if self.symbols[id].kind.is_function() => *symbol = Some(id),
+                        Some(id) => self.diagnostics.push(
+                            Diagnostic::error(
+                                "E0207",
+                                format!("`{}` is not a function", name),
+                                *span,
+                            )
+                            .with_note(format!(
+                                "`{}` is declared at {}",
+                                name,
+                                describe(self.symbols[id].span)
+                            )),
+                        ),
+                        None => self.unresolved(name, *span, "function"),
+                    }
+                } else {
+                    self.resolve_expr(callee);
+                    // Methods are looked up by the type checker, but the ones that change
+                    // the value need a mutable binding whatever the type is.
+                    if let Node::MemberExpr {
+                        object, property, ..
+                    } = callee.as_ref()
+                    {
+                        if let Node::Identifier { name, span, .. } = property.as_ref() {
+                            if runtime::MUTATING_METHODS.contains(&name.as_str()) {
+                                let what = match object.as_ref() {
+                                    Node::MemberExpr { .. } => " a part of",
+                                    _ => "",
+                                };
+                                self.check_mutable_root(
+                                    object,
+                                    &format!("call `{}` on{}", name, what),
+                                    *span,
+                                );
+                            }
+                        }
+                    }
+                }
+
+                for arg in args {
+                    self.resolve_expr(arg);
+                }
+            }
+            Node::BinaryExpr { left, right, .. } => {
+                // Resolve the value first, `x = x + 1` reads `x` before writing it.
+                self.resolve_expr(right);
+                self.resolve_expr(left);
+
+                if let Node::MemberExpr { .. } = left.as_ref() {
+                    self.check_field_assignment(left);
+                }
+            }
+            Node::MemberExpr {
+                object,
+                property,
+                computed,
+                ..
+            } => {
+                self.resolve_expr(object);
+                // Field names are looked up by the type checker.
+                if *computed {
+                    self.resolve_expr(property);
+                }
+            }
+            Node::StructLiteral { fields, .. } => {
+                for field in fields {
+                    if let Node::FieldInit { value, .. } = field {
+                        self.resolve_expr(value);
+                    }
+                }
+            }
+            Node::ArrayLiteral { elements, .. } | Node::EnumLiteral { args: elements, .. } => {
+                for element in elements {
+                    self.resolve_expr(element);
+                }
+            }
+            Node::Match {
+                scrutinee, arms, ..
+            } => {
+                self.resolve_expr(scrutinee);
+
+                for arm in arms {
+                    let Node::MatchArm { pattern, body, .. } = arm else {
+                        unreachable!()
+                    };
+
+                    self.scopes.push(Scope::default());
+                    if let Node::VariantPattern { bindings, .. } = pattern.as_mut() {
+                        for binding in bindings {
+                            if let Node::Identifier { name, symbol, span } = binding {
+                                if name != "_" {
+                                    *symbol =
+                                        Some(self.declare(name, SymbolKind::Binding, false, *span));
+                                }
+                            }
+                        }
+                    }
+                    match body.as_mut() {
+                        Node::Block { body, .. } => self.resolve_block(body),
+                        body => self.resolve_expr(body),
+                    }
+                    self.scopes.pop();
+                }
+            }
+            Node::UnaryExpr { operand, .. } => self.resolve_expr(operand),
+            Node::Cast { expr, .. } => self.resolve_expr(expr),
+            Node::Range { start, end, .. } => {
+                self.resolve_expr(start);
+                self.resolve_expr(end);
+            }
+            _ => {}
+        }
+    }
+}
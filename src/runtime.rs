@@ -0,0 +1,136 @@
+use crate::types::Type;
+
+/// Functions that every program can call without defining them.
+pub const BUILTINS: [&str; 2] = ["print", "println"];
+
+/// Methods that change the value they are called on.
+pub const MUTATING_METHODS: [&str; 2] = ["push", "pop"];
+
+/// C code emitted in front of every program; the functions are `static inline` so that
+/// the ones a program does not use do not trigger warnings.
+pub const PRELUDE: &str = r#"#include <inttypes.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <stdlib.h>
+
+/* Floats are printed like `%g` when that reads back as the same value, and with as many
+   more digits as it takes otherwise, so `0.1` prints as `0.1` but `0.1 + 0.2` does not hide
+   its rounding error. Fewer digits than `%g` has would turn `100` into `1e+02`. A `float`
+   is read back as a `float`, which needs fewer digits than the `double` it is passed as. */
+static inline void arlang_format_f64(char* buffer, double value) {
+    for (int precision = 6; precision < 17; precision++) {
+        snprintf(buffer, 32, "%.*g", precision, value);
+        if (strtod(buffer, NULL) == value) {
+            return;
+        }
+    }
+    snprintf(buffer, 32, "%.17g", value);
+}
+
+static inline void arlang_format_f32(char* buffer, float value) {
+    for (int precision = 6; precision < 9; precision++) {
+        snprintf(buffer, 32, "%.*g", precision, value);
+        if (strtof(buffer, NULL) == value) {
+            return;
+        }
+    }
+    snprintf(buffer, 32, "%.9g", value);
+}
+
+static inline void arlang_print_i64(int64_t value) { printf("%" PRId64, value); }
+static inline void arlang_print_u64(uint64_t value) { printf("%" PRIu64, value); }
+static inline void arlang_print_f32(float value) { char buffer[32]; arlang_format_f32(buffer, value); fputs(buffer, stdout); }
+static inline void arlang_print_f64(double value) { char buffer[32]; arlang_format_f64(buffer, value); fputs(buffer, stdout); }
+static inline void arlang_print_bool(bool value) { fputs(value ? "true" : "false", stdout); }
+static inline void arlang_print_str(const char* value) { fputs(value, stdout); }
+static inline void arlang_println_void(void) { putchar('\n'); }
+static inline void arlang_println_i64(int64_t value) { printf("%" PRId64 "\n", value); }
+static inline void arlang_println_u64(uint64_t value) { printf("%" PRIu64 "\n", value); }
+static inline void arlang_println_f32(float value) { char buffer[32]; arlang_format_f32(buffer, value); puts(buffer); }
+static inline void arlang_println_f64(double value) { char buffer[32]; arlang_format_f64(buffer, value); puts(buffer); }
+static inline void arlang_println_bool(bool value) { puts(value ? "true" : "false"); }
+static inline void arlang_println_str(const char* value) { puts(value); }
+
+static inline int64_t arlang_check_index(int64_t index, int64_t len) {
+    if (index < 0 || index >= len) {
+        fprintf(stderr, "index out of bounds: the length is %" PRId64 " but the index is %" PRId64 "\n", len, index);
+        exit(101);
+    }
+    return index;
+}
+
+#ifdef ARLANG_NO_BOUNDS_CHECKS
+#define ARLANG_CHECK_INDEX(index, len) (index)
+#else
+#define ARLANG_CHECK_INDEX(index, len) arlang_check_index((index), (len))
+#endif
+
+#define ARLANG_INDEX(array, index) \
+    ((array).items[ARLANG_CHECK_INDEX((index), sizeof((array).items) / sizeof((array).items[0]))])
+
+static inline void* arlang_alloc(void* ptr, size_t size) {
+    ptr = realloc(ptr, size);
+    if (ptr == NULL) {
+        fputs("out of memory\n", stderr);
+        exit(101);
+    }
+    return ptr;
+}
+
+/* Defines the `Vec` of `T` called `name`, after `typedef struct name name;`.
+   Vecs are never freed, they live as long as the program. */
+#define ARLANG_VEC(T, name) \
+    struct name { \
+        T* items; \
+        int64_t len; \
+        int64_t cap; \
+    }; \
+    static inline name* name##_from(T* items, int64_t len) { \
+        name* vec = arlang_alloc(NULL, sizeof(name)); \
+        vec->len = len; \
+        vec->cap = len > 4 ? len : 4; \
+        vec->items = arlang_alloc(NULL, sizeof(T) * vec->cap); \
+        for (int64_t i = 0; i < len; i++) { \
+            vec->items[i] = items[i]; \
+        } \
+        return vec; \
+    } \
+    static inline void name##_push(name* vec, T value) { \
+        if (vec->len == vec->cap) { \
+            vec->cap *= 2; \
+            vec->items = arlang_alloc(vec->items, sizeof(T) * vec->cap); \
+        } \
+        vec->items[vec->len++] = value; \
+    } \
+    static inline T name##_pop(name* vec) { \
+        if (vec->len == 0) { \
+            fputs("cannot pop from an empty Vec\n", stderr); \
+            exit(101); \
+        } \
+        return vec->items[--vec->len]; \
+    } \
+    static inline int64_t name##_len(name* vec) { return vec->len; } \
+    static inline T* name##_at(name* vec, int64_t index) { \
+        return &vec->items[ARLANG_CHECK_INDEX(index, vec->len)]; \
+    }
+"#;
+
+/// The runtime function that implements `builtin` for an argument of type `typ`,
+/// or `None` if it cannot take one. Only `println` can be called without an argument.
+pub fn builtin_function(builtin: &str, typ: Option<&Type>) -> Option<String> {
+    let suffix = match typ {
+        // Narrower integers are printed through the widest type of their kind, but a `f32`
+        // printed as a `f64` would show the digits of its rounding error.
+        Some(typ) if typ.is_signed() => "i64",
+        Some(typ) if typ.is_unsigned() => "u64",
+        Some(Type::F32) => "f32",
+        Some(typ) if typ.is_float() => "f64",
+        Some(Type::Bool) => "bool",
+        Some(Type::Str) => "str",
+        None if builtin == "println" => "void",
+        _ => return None,
+    };
+
+    Some(format!("arlang_{}_{}", builtin, suffix))
+}
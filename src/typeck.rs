@@ -0,0 +1,1890 @@
+use crate::diagnostics::Diagnostic;
+use crate::resolve::SymbolId;
+use crate::types::{Node, Span, Type};
+use crate::{monomorphize, runtime};
+use std::collections::{HashMap, HashSet};
+
+/// How many instantiations deep a generic function may be called from `main`.
+const MAX_INSTANTIATION_DEPTH: usize = 32;
+
+struct Signature {
+    /// Empty for ordinary functions.
+    type_params: Vec<String>,
+    params: Vec<Type>,
+    return_type: Type,
+}
+
+/// A generic function called with new type arguments, waiting to be checked.
+struct Instance {
+    /// The copy of the generic function with the type arguments filled in.
+    function: Node,
+    params: Vec<Type>,
+    return_type: Type,
+    /// The type arguments, as `` `T = int` `` for messages.
+    type_args: String,
+    /// The call that needed the instantiation first.
+    call_span: Span,
+    depth: usize,
+}
+
+struct Checker {
+    functions: HashMap<String, Signature>,
+    /// The fields of every struct, in declaration order.
+    structs: HashMap<String, Vec<(String, Type)>>,
+    /// The variants of every enum and the types of their fields, in declaration order.
+    enums: HashMap<String, Vec<(String, Vec<Type>)>>,
+    /// The generic functions, as written.
+    generics: HashMap<String, Node>,
+    /// The generic functions and type arguments of the instantiations found so far.
+    instantiated: Vec<(String, Vec<Type>)>,
+    pending: Vec<Instance>,
+    /// The type parameters of the signature or generic body being resolved.
+    type_params: Vec<String>,
+    /// Set while the body of a generic function is checked on its own, before any
+    /// instantiation; see `check_generic_body`.
+    generic_body: bool,
+    /// How many instantiations led to the function being checked.
+    depth: usize,
+    /// The types of the variables declared so far, by the symbols the resolver gave them.
+    variables: HashMap<SymbolId, Type>,
+    /// Return type of the function being checked.
+    return_type: Type,
+    diagnostics: Vec<Diagnostic>,
+}
+
+/// Checks that the program is well typed, so that the C compiler never sees type errors.
+///
+/// Types are inferred bidirectionally: the type a context expects is passed down, so that
+/// unsuffixed literals can take it, and the types found are passed back up. The inferred
+/// types of local variables are recorded in `VarDecl::resolved_type` for code generation.
+///
+/// Generic functions are monomorphized here too: the body of a generic function is checked
+/// on its own, and again for every combination of type arguments it is called with, and the
+/// program ends up with one ordinary function per combination in its place.
+pub fn check(program: &mut Node) -> Vec<Diagnostic> {
+    let mut checker = Checker {
+        functions: HashMap::new(),
+        structs: HashMap::new(),
+        enums: HashMap::new(),
+        generics: HashMap::new(),
+        instantiated: vec![],
+        pending: vec![],
+        type_params: vec![],
+        generic_body: false,
+        depth: 0,
+        variables: HashMap::new(),
+        return_type: Type::Void,
+        diagnostics: vec![],
+    };
+
+    checker.check_program(program);
+    checker.diagnostics
+}
+
+impl Checker {
+    fn error(&mut self, code: &'static str, message: impl Into<String>, span: Span) {
+        self.diagnostics
+            .push(Diagnostic::error(code, message, span));
+    }
+
+    /// Reports a mismatch unless `found` is `expected`, or widens to it without loss;
+    /// errors never cause further errors.
+    fn expect_type(&mut self, expected: &Type, found: &Type, span: Span) -> bool {
+        if found.widens_to(expected) || *expected == Type::Error || *found == Type::Error {
+            return true;
+        }
+
+        let mut diagnostic = Diagnostic::error(
+            "E0307",
+            format!(
+                "mismatched types: expected `{}`, found `{}`",
+                expected, found
+            ),
+            span,
+        )
+        .with_label(format!("expected `{}`", expected));
+        if expected.is_numeric() && found.is_numeric() {
+            diagnostic = diagnostic.with_help(format!("convert the value with `as {}`", expected));
+        }
+
+        self.diagnostics.push(diagnostic);
+        false
+    }
+
+    /// Resolves the type of a value: anything but the return type of a function.
+    fn resolve_value_type(&mut self, typ: &Node) -> Type {
+        let resolved = self.resolve_type(typ);
+        if resolved != Type::Void {
+            return resolved;
+        }
+
+        self.diagnostics.push(
+            Diagnostic::error("E0333", "`void` is not the type of any value", typ.span())
+                .with_help("`void` can only be the return type of a function"),
+        );
+        Type::Error
+    }
+
+    fn resolve_type(&mut self, typ: &Node) -> Type {
+        match typ {
+            Node::Identifier { name, .. } if self.type_params.contains(name) => {
+                if self.generic_body {
+                    Type::Error
+                } else {
+                    Type::Param(name.clone())
+                }
+            }
+            Node::Identifier { name, span, .. } => match Type::from_name(name) {
+                Some(typ) => typ,
+                None if self.structs.contains_key(name) => Type::Struct(name.clone()),
+                None if self.enums.contains_key(name) => Type::Enum(name.clone()),
+                None if name == "Vec" => {
+                    self.diagnostics.push(
+                        Diagnostic::error("E0324", "missing type argument for `Vec`", *span)
+                            .with_help("write the element type, as in `Vec<int>`"),
+                    );
+                    Type::Error
+                }
+                None => {
+                    self.error("E0301", format!("cannot find type `{}`", name), *span);
+                    Type::Error
+                }
+            },
+            Node::ArrayType { element, len, span } => {
+                let element = self.resolve_value_type(element);
+                if *len == 0 {
+                    self.error("E0319", "arrays must have at least one element", *span);
+                    return Type::Error;
+                }
+                if element == Type::Error {
+                    return Type::Error;
+                }
+
+                Type::Array(element.into(), *len)
+            }
+            Node::GenericType { name, args, span } => {
+                let args = args
+                    .iter()
+                    .map(|arg| self.resolve_value_type(arg))
+                    .collect::<Vec<_>>();
+                if name != "Vec" {
+                    if Type::from_name(name).is_some()
+                        || self.type_params.contains(name)
+                        || self.structs.contains_key(name)
+                        || self.enums.contains_key(name)
+                    {
+                        self.error(
+                            "E0324",
+                            format!("type `{}` does not take type arguments", name),
+                            *span,
+                        );
+                    } else {
+                        self.error("E0301", format!("cannot find type `{}`", name), *span);
+                    }
+                    return Type::Error;
+                }
+
+                match args.as_slice() {
+                    [Type::Error] => Type::Error,
+                    [element] => Type::Vec(element.clone().into()),
+                    _ => {
+                        self.error(
+                            "E0324",
+                            format!(
+                                "`Vec` takes 1 type argument but {} were supplied",
+                                args.len()
+                            ),
+                            *span,
+                        );
+                        Type::Error
+                    }
+                }
+            }
+            _ => unreachable!("{:?} is not a type", typ),
+        }
+    }
+
+    /// The type of the variable `symbol` refers to; `None` for unknown names and functions.
+    fn lookup(&self, symbol: Option<SymbolId>) -> Option<&Type> {
+        self.variables.get(&symbol?)
+    }
+
+    fn declare(&mut self, symbol: Option<SymbolId>, typ: Type) {
+        self.variables
+            .insert(symbol.expect("declarations are resolved"), typ);
+    }
+
+    fn check_program(&mut self, program: &mut Node) {
+        let Node::Program { body, .. } = program else {
+            unreachable!()
+        };
+
+        self.collect_types(body);
+
+        // Collect every signature first, so that functions can be called before they are defined.
+        for stmt in body.iter() {
+            if let Node::Function {
+                name,
+                type_params,
+                params,
+                return_type,
+                span,
+                ..
+            } = stmt
+            {
+                self.type_params = self.declare_type_params(type_params);
+                let signature = Signature {
+                    params: params
+                        .iter()
+                        .map(|param| match param {
+                            Node::TypedIdentifier { typ, .. } => self.resolve_value_type(typ),
+                            _ => unreachable!(),
+                        })
+                        .collect(),
+                    return_type: match return_type {
+                        Some(typ) => self.resolve_type(typ),
+                        None => Type::Void,
+                    },
+                    // Last, the parameters above are resolved while it is in scope.
+                    type_params: std::mem::take(&mut self.type_params),
+                };
+
+                for type_param in type_params {
+                    let Node::Identifier {
+                        name: type_param,
+                        span,
+                        ..
+                    } = type_param
+                    else {
+                        unreachable!()
+                    };
+                    let used = signature.params.iter().any(|typ| {
+                        monomorphize::type_params_of(typ).contains(&type_param.as_str())
+                    });
+                    if !used {
+                        self.diagnostics.push(
+                            Diagnostic::error(
+                                "E0328",
+                                format!(
+                                    "type parameter `{}` is not used by the parameters of `{}`",
+                                    type_param, name
+                                ),
+                                *span,
+                            )
+                            .with_help(
+                                "its type could never be inferred from the arguments of a call",
+                            ),
+                        );
+                    }
+                }
+
+                if name == "main" && !signature.type_params.is_empty() {
+                    self.diagnostics.push(
+                        Diagnostic::error("E0311", "`main` cannot be generic", *span)
+                            .with_help("declare it as `fn main() { ... }`"),
+                    );
+                }
+                if name == "main"
+                    && (!signature.params.is_empty() || signature.return_type != Type::Void)
+                {
+                    self.diagnostics.push(
+                        Diagnostic::error(
+                            "E0311",
+                            "`main` must not take parameters or return a value",
+                            *span,
+                        )
+                        .with_help("declare it as `fn main() { ... }`"),
+                    );
+                }
+
+                // C keeps type and function names in the same namespace.
+                let kind = if self.structs.contains_key(name) {
+                    Some("a struct")
+                } else if self.enums.contains_key(name) {
+                    Some("an enum")
+                } else {
+                    None
+                };
+                if let Some(kind) = kind {
+                    self.error(
+                        "E0304",
+                        format!(
+                            "the name `{}` is used by both {} and a function",
+                            name, kind
+                        ),
+                        *span,
+                    );
+                }
+
+                // Duplicate definitions are reported by the resolver.
+                if !self.functions.contains_key(name) && !signature.type_params.is_empty() {
+                    self.generics.insert(name.clone(), stmt.clone());
+                }
+                self.functions.entry(name.clone()).or_insert(signature);
+            }
+        }
+
+        let mut checked = HashSet::new();
+        for stmt in body.iter_mut() {
+            match stmt {
+                // The body of a duplicate would be checked against the first signature.
+                Node::Function { name, .. } if !checked.insert(name.clone()) => {}
+                Node::Function { type_params, .. } if !type_params.is_empty() => {
+                    self.check_generic_body(stmt)
+                }
+                Node::Function { name, .. } => {
+                    let signature = &self.functions[name];
+                    let (params, return_type) =
+                        (signature.params.clone(), signature.return_type.clone());
+                    self.check_function(stmt, &params, return_type);
+                }
+                Node::Struct { .. } | Node::Enum { .. } => {}
+                _ => self.diagnostics.push(
+                    Diagnostic::error("E0300", "expected a function", stmt.span())
+                        .with_note("only functions can be declared at the top level"),
+                ),
+            }
+        }
+
+        // Checking an instantiation can find calls that need more of them.
+        let mut instances = vec![];
+        while !self.pending.is_empty() {
+            let instance = self.pending.remove(0);
+            instances.push(self.check_instance(instance));
+        }
+        body.retain(
+            |stmt| !matches!(stmt, Node::Function { type_params, .. } if !type_params.is_empty()),
+        );
+        body.extend(instances);
+    }
+
+    fn declare_type_params(&mut self, type_params: &[Node]) -> Vec<String> {
+        let mut names: Vec<String> = vec![];
+        for type_param in type_params {
+            let Node::Identifier { name, span, .. } = type_param else {
+                unreachable!()
+            };
+            if names.contains(name) {
+                self.error(
+                    "E0304",
+                    format!("type parameter `{}` is declared more than once", name),
+                    *span,
+                );
+            } else {
+                names.push(name.clone());
+            }
+        }
+        names
+    }
+
+    /// Checks the body of a generic function once, so that its errors are reported even if
+    /// nothing calls it. Values whose type depends on the type parameters are given the
+    /// error type, which is compatible with everything, so only the errors that no type
+    /// arguments could fix are found here; the others are found in `check_instance`.
+    fn check_generic_body(&mut self, function: &mut Node) {
+        let Node::Function {
+            name, type_params, ..
+        } = function
+        else {
+            unreachable!()
+        };
+        let signature = &self.functions[name.as_str()];
+        let opaque = |typ: &Type| match monomorphize::type_params_of(typ).is_empty() {
+            true => typ.clone(),
+            false => Type::Error,
+        };
+        let params = signature.params.iter().map(opaque).collect::<Vec<_>>();
+        let return_type = opaque(&signature.return_type);
+
+        self.type_params = type_params
+            .iter()
+            .map(|param| match param {
+                Node::Identifier { name, .. } => name.clone(),
+                _ => unreachable!(),
+            })
+            .collect();
+        self.generic_body = true;
+        self.check_function(function, &params, return_type);
+        self.generic_body = false;
+        self.type_params.clear();
+    }
+
+    fn check_instance(&mut self, instance: Instance) -> Node {
+        let mut function = instance.function;
+        let Node::Function { name, .. } = &function else {
+            unreachable!()
+        };
+        let name = name.clone();
+
+        self.depth = instance.depth;
+        let start = self.diagnostics.len();
+        self.check_function(&mut function, &instance.params, instance.return_type);
+
+        // The diagnostics that do not depend on the type arguments were reported when the
+        // generic body was checked on its own. Any other error means that the generic function
+        // does not support these type arguments, so it is reported at the call that chose them.
+        let mut errors = vec![];
+        for diagnostic in self.diagnostics.split_off(start) {
+            let reported = self
+                .diagnostics
+                .iter()
+                .any(|other| other.code == diagnostic.code && other.span == diagnostic.span);
+            // Too deep instantiations are about the call itself already.
+            if reported {
+                continue;
+            } else if diagnostic.is_error() && diagnostic.code != "E0330" {
+                errors.push(diagnostic);
+            } else {
+                self.diagnostics.push(diagnostic);
+            }
+        }
+        if !errors.is_empty() {
+            let mut diagnostic = Diagnostic::error(
+                "E0329",
+                format!("`{}` cannot be called with {}", name, instance.type_args),
+                instance.call_span,
+            );
+            for error in errors {
+                diagnostic = diagnostic.with_note(format!(
+                    "in the body of `{}`, at {}:{}: {}",
+                    name, error.span.line, error.span.col, error.message
+                ));
+            }
+            self.diagnostics.push(diagnostic);
+        }
+
+        function
+    }
+
+    fn collect_types(&mut self, body: &[Node]) {
+        // Names first, so that fields can refer to any type.
+        let mut definitions = HashMap::new();
+        for stmt in body {
+            let (kind, name, span) = match stmt {
+                Node::Struct { name, span, .. } => ("struct", name, span),
+                Node::Enum { name, span, .. } => ("enum", name, span),
+                _ => continue,
+            };
+
+            if Type::from_name(name).is_some() || name == "Vec" {
+                self.error(
+                    "E0304",
+                    format!("`{}` is a builtin type and cannot be redefined", name),
+                    *span,
+                );
+            } else if definitions.contains_key(name) {
+                self.error(
+                    "E0304",
+                    format!("the {} `{}` is defined more than once", kind, name),
+                    *span,
+                );
+            } else {
+                definitions.insert(name.clone(), *span);
+                if kind == "struct" {
+                    self.structs.insert(name.clone(), vec![]);
+                } else {
+                    self.enums.insert(name.clone(), vec![]);
+                }
+            }
+        }
+
+        for stmt in body {
+            let Node::Struct { name, fields, span } = stmt else {
+                continue;
+            };
+            if definitions.get(name) != Some(span) {
+                continue;
+            }
+
+            if fields.is_empty() {
+                self.diagnostics.push(
+                    Diagnostic::error("E0318", format!("struct `{}` has no fields", name), *span)
+                        .with_note("C does not allow empty structs"),
+                );
+            }
+
+            let mut resolved: Vec<(String, Type)> = vec![];
+            for field in fields {
+                let Node::TypedIdentifier {
+                    name: field_name,
+                    typ,
+                    span,
+                    ..
+                } = field
+                else {
+                    unreachable!()
+                };
+
+                let typ = self.resolve_value_type(typ);
+                if resolved.iter().any(|(other, _)| other == field_name) {
+                    self.error(
+                        "E0304",
+                        format!("field `{}` is declared more than once", field_name),
+                        *span,
+                    );
+                } else {
+                    resolved.push((field_name.clone(), typ));
+                }
+            }
+            self.structs.insert(name.clone(), resolved);
+        }
+
+        for stmt in body {
+            let Node::Enum {
+                name,
+                variants,
+                span,
+            } = stmt
+            else {
+                continue;
+            };
+            if definitions.get(name) != Some(span) {
+                continue;
+            }
+
+            if variants.is_empty() {
+                self.diagnostics.push(
+                    Diagnostic::error("E0318", format!("enum `{}` has no variants", name), *span)
+                        .with_note("C does not allow empty enums"),
+                );
+            }
+
+            let mut resolved: Vec<(String, Vec<Type>)> = vec![];
+            for variant in variants {
+                let Node::Variant {
+                    name: variant_name,
+                    fields,
+                    span,
+                } = variant
+                else {
+                    unreachable!()
+                };
+
+                let fields = fields
+                    .iter()
+                    .map(|field| self.resolve_value_type(field))
+                    .collect();
+                if resolved.iter().any(|(other, _)| other == variant_name) {
+                    self.error(
+                        "E0304",
+                        format!("variant `{}` is declared more than once", variant_name),
+                        *span,
+                    );
+                } else {
+                    resolved.push((variant_name.clone(), fields));
+                }
+            }
+            self.enums.insert(name.clone(), resolved);
+        }
+
+        for stmt in body {
+            let (kind, name, span) = match stmt {
+                Node::Struct { name, span, .. } => ("struct", name, span),
+                Node::Enum { name, span, .. } => ("enum", name, span),
+                _ => continue,
+            };
+            if definitions.get(name) == Some(span) && self.is_recursive(name) {
+                let help = if kind == "struct" {
+                    "a struct cannot have a field of its own type, directly or through other structs"
+                } else {
+                    "a variant cannot hold its own enum, directly or through other types; a `Vec` can"
+                };
+                self.diagnostics.push(
+                    Diagnostic::error(
+                        "E0317",
+                        format!("{} `{}` contains itself and has infinite size", kind, name),
+                        *span,
+                    )
+                    .with_help(help),
+                );
+            }
+        }
+    }
+
+    /// The types a struct or enum holds by value.
+    fn contained_types(&self, name: &str) -> Vec<&Type> {
+        match self.structs.get(name) {
+            Some(fields) => fields.iter().map(|(_, typ)| typ).collect(),
+            None => self.enums[name]
+                .iter()
+                .flat_map(|(_, fields)| fields)
+                .collect(),
+        }
+    }
+
+    /// Whether the struct or enum contains itself by value, through any chain of fields.
+    fn is_recursive(&self, name: &str) -> bool {
+        let mut stack = vec![name];
+        let mut seen = HashSet::new();
+
+        while let Some(current) = stack.pop() {
+            for typ in self.contained_types(current) {
+                // Arrays hold their elements by value too.
+                let mut typ = typ;
+                while let Type::Array(element, _) = typ {
+                    typ = element;
+                }
+
+                if let Type::Struct(inner) | Type::Enum(inner) = typ {
+                    if inner == name {
+                        return true;
+                    }
+                    if seen.insert(inner.as_str()) {
+                        stack.push(inner);
+                    }
+                }
+            }
+        }
+
+        false
+    }
+
+    fn check_function(&mut self, function: &mut Node, param_types: &[Type], return_type: Type) {
+        let Node::Function {
+            name,
+            params,
+            body,
+            span,
+            ..
+        } = function
+        else {
+            unreachable!()
+        };
+
+        self.return_type = return_type;
+
+        for (param, typ) in params.iter().zip(param_types) {
+            if let Node::TypedIdentifier { symbol, .. } = param {
+                self.declare(*symbol, typ.clone());
+            }
+        }
+        self.check_block(body);
+
+        if !matches!(self.return_type, Type::Void | Type::Error) && !always_returns(body) {
+            self.diagnostics.push(
+                Diagnostic::error(
+                    "E0310",
+                    format!("function `{}` does not return a value on every path", name),
+                    *span,
+                )
+                .with_help(format!(
+                    "add a `return` with a value of type `{}`",
+                    self.return_type
+                )),
+            );
+        }
+    }
+
+    fn check_block(&mut self, body: &mut [Node]) {
+        for stmt in body {
+            self.check_stmt(stmt);
+        }
+    }
+
+    fn check_condition(&mut self, condition: &mut Node) {
+        let typ = self.check_expr(condition, Some(&Type::Bool));
+        self.expect_type(&Type::Bool, &typ, condition.span());
+    }
+
+    fn check_stmt(&mut self, stmt: &mut Node) {
+        match stmt {
+            Node::VarDecl {
+                type_annotation,
+                init,
+                symbol,
+                resolved_type,
+                ..
+            } => {
+                let annotation = type_annotation
+                    .as_ref()
+                    .map(|annotation| self.resolve_value_type(annotation));
+
+                let mut init_type = self.check_expr(init, annotation.as_ref());
+                if init_type == Type::Void {
+                    self.error(
+                        "E0308",
+                        "this expression does not produce a value",
+                        init.span(),
+                    );
+                    init_type = Type::Error;
+                }
+
+                let typ = match annotation {
+                    Some(typ) => {
+                        self.expect_type(&typ, &init_type, init.span());
+                        typ
+                    }
+                    None => init_type,
+                };
+                *resolved_type = Some(typ.clone());
+                self.declare(*symbol, typ);
+            }
+            Node::If {
+                condition,
+                then_body,
+                else_body,
+                ..
+            } => {
+                self.check_condition(condition);
+                self.check_block(then_body);
+                if let Some(else_body) = else_body {
+                    self.check_block(else_body);
+                }
+            }
+            Node::While {
+                condition, body, ..
+            } => {
+                self.check_condition(condition);
+                self.check_block(body);
+            }
+            Node::For {
+                iter,
+                symbol,
+                iter_type,
+                body,
+                ..
+            } => {
+                let var_type = if let Node::Range { start, end, .. } = iter.as_mut() {
+                    for bound in [start, end] {
+                        let typ = self.check_expr(bound, Some(&Type::I64));
+                        self.expect_type(&Type::I64, &typ, bound.span());
+                    }
+                    Type::I64
+                } else {
+                    let typ = self.check_expr(iter, None);
+                    *iter_type = Some(typ.clone());
+                    match typ {
+                        Type::Vec(element) => *element,
+                        Type::Error => Type::Error,
+                        _ => {
+                            self.diagnostics.push(
+                                Diagnostic::error(
+                                    "E0322",
+                                    format!("cannot iterate over a value of type `{}`", typ),
+                                    iter.span(),
+                                )
+                                .with_help(
+                                    "`for` loops iterate over ranges, as in `0..10`, and `Vec`s",
+                                ),
+                            );
+                            Type::Error
+                        }
+                    }
+                };
+
+                self.declare(*symbol, var_type);
+                self.check_block(body);
+            }
+            Node::Return { value, span } => {
+                let return_type = self.return_type.clone();
+                match (value, &return_type) {
+                    (None, Type::Void | Type::Error) => {}
+                    (None, _) => self.error(
+                        "E0309",
+                        format!(
+                            "`return` without a value in a function that returns `{}`",
+                            return_type
+                        ),
+                        *span,
+                    ),
+                    (Some(value), Type::Void) => {
+                        self.check_expr(value, None);
+                        self.diagnostics.push(
+                            Diagnostic::error(
+                                "E0309",
+                                "this function does not return a value",
+                                value.span(),
+                            )
+                            .with_help("declare the return type with `-> type`"),
+                        );
+                    }
+                    (Some(value), _) => {
+                        let typ = self.check_expr(value, Some(&return_type));
+                        self.expect_type(&return_type, &typ, value.span());
+                    }
+                }
+            }
+            Node::Break { .. } | Node::Continue { .. } => {}
+            Node::Function { span, .. } => self.diagnostics.push(
+                Diagnostic::error(
+                    "E0300",
+                    "functions cannot be declared inside other functions",
+                    *span,
+                )
+                .with_help("move the function to the top level"),
+            ),
+            Node::Struct { span, .. } => self.diagnostics.push(
+                Diagnostic::error(
+                    "E0300",
+                    "structs cannot be declared inside functions",
+                    *span,
+                )
+                .with_help("move the struct to the top level"),
+            ),
+            Node::Enum { span, .. } => self.diagnostics.push(
+                Diagnostic::error("E0300", "enums cannot be declared inside functions", *span)
+                    .with_help("move the enum to the top level"),
+            ),
+            // The arms of a `match` statement do not need a common type.
+            Node::Match { .. } => {
+                self.check_match(stmt, None, true);
+            }
+            _ => {
+                self.check_expr(stmt, None);
+            }
+        }
+    }
+
+    /// Returns the type of `expr`; `expected` is the type the surrounding code wants, if known.
+    fn check_expr(&mut self, expr: &mut Node, expected: Option<&Type>) -> Type {
+        match expr {
+            Node::StringLiteral { .. } => Type::Str,
+            Node::BoolLiteral { .. } => Type::Bool,
+            Node::NumericLiteral { .. } => self.check_literal(expr, expected, false),
+            // Unknown names are reported by the resolver.
+            Node::Identifier { symbol, .. } => self.lookup(*symbol).cloned().unwrap_or(Type::Error),
+            Node::UnaryExpr {
+                operand,
+                operator,
+                resolved_type,
+                span,
+            } => {
+                let typ = match operator.as_str() {
+                    "-" if matches!(**operand, Node::NumericLiteral { .. }) => {
+                        self.check_literal(operand, expected, true)
+                    }
+                    "-" => self.check_expr(operand, expected),
+                    _ => self.check_expr(operand, Some(&Type::Bool)),
+                };
+                let valid = match operator.as_str() {
+                    "-" => typ.is_numeric() && !typ.is_unsigned(),
+                    _ => typ == Type::Bool,
+                };
+
+                if typ == Type::Error || valid {
+                    *resolved_type = Some(typ.clone());
+                    typ
+                } else {
+                    self.error(
+                        "E0305",
+                        format!("cannot apply unary `{}` to `{}`", operator, typ),
+                        *span,
+                    );
+                    Type::Error
+                }
+            }
+            Node::BinaryExpr {
+                left,
+                right,
+                operator,
+                resolved_type,
+                span,
+            } => {
+                let typ = self.check_binary(left, right, operator, *span, expected);
+                *resolved_type = Some(typ.clone());
+                typ
+            }
+            Node::Cast { expr, typ, span } => {
+                let target = self.resolve_type(typ);
+                let source = self.check_expr(expr, None);
+
+                // Numbers convert freely between each other, `bool` only turns into integers
+                // or stays a `bool`. C has no casts between structs, arrays or strings.
+                let valid = (source.is_numeric() && target.is_numeric())
+                    || (source == Type::Bool && (target.is_integer() || target == Type::Bool));
+                if !valid && source != Type::Error && target != Type::Error {
+                    self.error(
+                        "E0314",
+                        format!("cannot cast `{}` as `{}`", source, target),
+                        *span,
+                    );
+                    return Type::Error;
+                }
+
+                target
+            }
+            Node::CallExpr {
+                callee,
+                args,
+                type_args,
+                span,
+            } => self.check_call(callee, args, type_args, *span),
+            Node::StructLiteral { name, fields, span } => {
+                self.check_struct_literal(name, fields, *span)
+            }
+            Node::EnumLiteral {
+                name,
+                variant,
+                args,
+                span,
+            } => self.check_enum_literal(name, variant, args, *span),
+            Node::Match { .. } => self.check_match(expr, expected, false),
+            Node::ArrayLiteral {
+                elements,
+                resolved_type,
+                span,
+            } => {
+                // A list literal where a `Vec` is expected creates one from its elements.
+                let into_vec = matches!(expected, Some(Type::Vec(_)));
+                if elements.is_empty() && expected == Some(&Type::Error) {
+                    return Type::Error;
+                }
+                if elements.is_empty() && !into_vec {
+                    self.diagnostics.push(
+                        Diagnostic::error("E0319", "arrays must have at least one element", *span)
+                            .with_help(
+                                "an empty list needs a `Vec` type, as in `let list: Vec<int> = []`",
+                            ),
+                    );
+                    return Type::Error;
+                }
+
+                // The element type comes from the context, or else from the first element.
+                let mut element_type = match expected {
+                    Some(Type::Array(element, _) | Type::Vec(element)) => {
+                        Some(element.as_ref().clone())
+                    }
+                    _ => None,
+                };
+                for element in elements.iter_mut() {
+                    let mut found = self.check_expr(element, element_type.as_ref());
+                    if found == Type::Void {
+                        self.error(
+                            "E0308",
+                            "this expression does not produce a value",
+                            element.span(),
+                        );
+                        found = Type::Error;
+                    }
+                    match &element_type {
+                        Some(typ) => {
+                            self.expect_type(typ, &found, element.span());
+                        }
+                        None => element_type = Some(found),
+                    }
+                }
+
+                let element_type = element_type.unwrap();
+                if element_type == Type::Error {
+                    return Type::Error;
+                }
+                let typ = if into_vec {
+                    Type::Vec(element_type.into())
+                } else {
+                    Type::Array(element_type.into(), elements.len())
+                };
+                *resolved_type = Some(typ.clone());
+                typ
+            }
+            Node::MemberExpr {
+                object,
+                property,
+                computed: true,
+                object_type: resolved_object_type,
+                ..
+            } => {
+                let object_type = self.check_expr(object, None);
+                *resolved_object_type = Some(object_type.clone());
+                let index_type = self.check_expr(property, Some(&Type::I64));
+                if index_type != Type::Error && !index_type.is_integer() {
+                    self.error(
+                        "E0307",
+                        format!("array indices must be integers, found `{}`", index_type),
+                        property.span(),
+                    );
+                }
+
+                match object_type {
+                    Type::Error => Type::Error,
+                    Type::Array(element, len) => {
+                        if let Node::NumericLiteral { val, span, .. } = property.as_ref() {
+                            if val.parse::<u64>().is_ok_and(|index| index >= len as u64) {
+                                self.error(
+                                    "E0320",
+                                    format!(
+                                        "index out of bounds: the length is {} but the index is {}",
+                                        len, val
+                                    ),
+                                    *span,
+                                );
+                            }
+                        }
+                        *element
+                    }
+                    // The length of a `Vec` is only known at runtime.
+                    Type::Vec(element) => *element,
+                    _ => {
+                        self.error(
+                            "E0321",
+                            format!("cannot index into a value of type `{}`", object_type),
+                            object.span(),
+                        );
+                        Type::Error
+                    }
+                }
+            }
+            Node::MemberExpr {
+                object,
+                property,
+                computed: false,
+                ..
+            } => {
+                let object_type = self.check_expr(object, None);
+                let Node::Identifier {
+                    name: field,
+                    span: field_span,
+                    ..
+                } = property.as_ref()
+                else {
+                    unreachable!("the parser only produces identifiers as field names")
+                };
+
+                let field_type = match &object_type {
+                    Type::Error => return Type::Error,
+                    Type::Struct(name) => self.structs[name]
+                        .iter()
+                        .find(|(other, _)| other == field)
+                        .map(|(_, typ)| typ.clone()),
+                    _ => None,
+                };
+
+                field_type.unwrap_or_else(|| {
+                    self.error(
+                        "E0315",
+                        format!("no field `{}` on type `{}`", field, object_type),
+                        *field_span,
+                    );
+                    Type::Error
+                })
+            }
+            _ => unreachable!("{:?} is not an expression", expr),
+        }
+    }
+
+    /// Types a number literal and checks that it fits; `negated` is set for the operand of a
+    /// unary `-`, as signed types reach one further below zero than above it.
+    fn check_literal(
+        &mut self,
+        literal: &mut Node,
+        expected: Option<&Type>,
+        negated: bool,
+    ) -> Type {
+        let Node::NumericLiteral { typ, val, span } = literal else {
+            unreachable!()
+        };
+        let default = Type::from_name(typ).expect("the lexer only accepts known suffixes");
+
+        // Unsuffixed literals take the expected type, as long as it is the same kind of number.
+        let literal_type = match expected {
+            Some(expected)
+                if is_unsuffixed(typ)
+                    && ((expected.is_integer() && default.is_integer())
+                        || (expected.is_float() && default.is_float())) =>
+            {
+                *typ = expected.to_string();
+                expected.clone()
+            }
+            _ => default,
+        };
+
+        if literal_type.is_integer() {
+            let max = literal_type.max_value();
+            let limit = max + (negated && literal_type.is_signed()) as u64;
+
+            if val.parse::<u64>().map_or(true, |val| val > limit) {
+                let min = if literal_type.is_signed() {
+                    format!("-{}", max as u128 + 1)
+                } else {
+                    "0".into()
+                };
+                self.diagnostics.push(
+                    Diagnostic::error(
+                        "E0313",
+                        format!("literal out of range for `{}`", literal_type),
+                        *span,
+                    )
+                    .with_note(format!(
+                        "`{}` holds values from `{}` to `{}`",
+                        literal_type, min, max
+                    )),
+                );
+                return Type::Error;
+            }
+        }
+
+        literal_type
+    }
+
+    fn check_binary(
+        &mut self,
+        left: &mut Node,
+        right: &mut Node,
+        operator: &str,
+        span: Span,
+        expected: Option<&Type>,
+    ) -> Type {
+        if matches!(operator, "=" | "+=" | "-=" | "*=" | "/=" | "%=") {
+            return self.check_assignment(left, right, operator);
+        }
+
+        // Arithmetic produces a value of the operand type, so the expectation applies to the operands.
+        let operand_expected = match operator {
+            "+" | "-" | "*" | "/" | "%" => expected,
+            "&&" | "||" => Some(&Type::Bool),
+            _ => None,
+        };
+
+        // Infer the typed side first, so that a literal on the left can take the type of the right.
+        let (left_type, right_type) = if is_unsuffixed_expr(left) && !is_unsuffixed_expr(right) {
+            let right_type = self.check_expr(right, operand_expected);
+            (self.check_expr(left, Some(&right_type)), right_type)
+        } else {
+            let left_type = self.check_expr(left, operand_expected);
+            let right_type = self.check_expr(right, Some(&left_type));
+            (left_type, right_type)
+        };
+        if left_type == Type::Error || right_type == Type::Error {
+            return Type::Error;
+        }
+
+        // The narrower operand is widened to the type of the other one.
+        let common = if right_type.widens_to(&left_type) {
+            Some(left_type.clone())
+        } else if left_type.widens_to(&right_type) {
+            Some(right_type.clone())
+        } else {
+            None
+        };
+
+        let result = match (operator, common) {
+            ("+" | "-" | "*" | "/", Some(common)) if common.is_numeric() => Some(common),
+            ("%", Some(common)) if common.is_integer() => Some(common),
+            ("<" | ">" | "<=" | ">=", Some(common)) if common.is_numeric() => Some(Type::Bool),
+            ("==" | "!=", Some(common)) if common.is_numeric() || common == Type::Bool => {
+                Some(Type::Bool)
+            }
+            ("&&" | "||", Some(Type::Bool)) => Some(Type::Bool),
+            _ => None,
+        };
+
+        result.unwrap_or_else(|| {
+            let mut diagnostic = Diagnostic::error(
+                "E0305",
+                format!(
+                    "cannot apply `{}` to `{}` and `{}`",
+                    operator, left_type, right_type
+                ),
+                span,
+            );
+
+            for (operand, other) in [(&*left, &right_type), (&*right, &left_type)] {
+                if let Node::NumericLiteral { typ, val, .. } = operand {
+                    if typ == "int" && other.is_float() {
+                        diagnostic = diagnostic
+                            .with_help(format!("use `{}.0` to write a float literal", val));
+                    }
+                }
+            }
+
+            self.diagnostics.push(diagnostic);
+            Type::Error
+        })
+    }
+
+    fn check_assignment(&mut self, target: &mut Node, value: &mut Node, operator: &str) -> Type {
+        let target_type = match target {
+            Node::Variable { symbol, .. } => self.lookup(*symbol).cloned(),
+            Node::MemberExpr { .. } => Some(self.check_expr(target, None)),
+            _ => unreachable!("the parser only produces assignments to variables and fields"),
+        };
+        let Some(target_type) = target_type else {
+            self.check_expr(value, None);
+            return Type::Void;
+        };
+
+        let value_type = self.check_expr(value, Some(&target_type));
+
+        if self.expect_type(&target_type, &value_type, value.span()) && operator != "=" {
+            let valid = match operator {
+                "%=" => target_type.is_integer(),
+                _ => target_type.is_numeric(),
+            };
+            if !valid && target_type != Type::Error {
+                self.error(
+                    "E0305",
+                    format!("cannot apply `{}` to `{}`", operator, target_type),
+                    target.span().to(value.span()),
+                );
+            }
+        }
+
+        // Assignments are statements; they do not produce a value.
+        Type::Void
+    }
+
+    fn check_struct_literal(&mut self, name: &str, fields: &mut [Node], span: Span) -> Type {
+        let Some(declared) = self.structs.get(name).cloned() else {
+            for field in fields.iter_mut() {
+                if let Node::FieldInit { value, .. } = field {
+                    self.check_expr(value, None);
+                }
+            }
+            self.error("E0301", format!("cannot find struct `{}`", name), span);
+            return Type::Error;
+        };
+
+        let mut initialized = HashSet::new();
+        for field in fields.iter_mut() {
+            let Node::FieldInit {
+                name: field_name,
+                value,
+                span: field_span,
+            } = field
+            else {
+                unreachable!()
+            };
+
+            let field_type = declared
+                .iter()
+                .find(|(other, _)| other == field_name)
+                .map(|(_, typ)| typ.clone());
+            let value_type = self.check_expr(value, field_type.as_ref());
+
+            match field_type {
+                None => self.error(
+                    "E0315",
+                    format!("struct `{}` has no field named `{}`", name, field_name),
+                    *field_span,
+                ),
+                Some(_) if !initialized.insert(field_name.clone()) => self.error(
+                    "E0316",
+                    format!("field `{}` is initialized more than once", field_name),
+                    *field_span,
+                ),
+                Some(field_type) => {
+                    self.expect_type(&field_type, &value_type, value.span());
+                }
+            }
+        }
+
+        let missing = declared
+            .iter()
+            .filter(|(field, _)| !initialized.contains(field))
+            .map(|(field, _)| format!("`{}`", field))
+            .collect::<Vec<_>>();
+        if !missing.is_empty() {
+            self.error(
+                "E0316",
+                format!(
+                    "missing field{} {} in initializer of `{}`",
+                    if missing.len() == 1 { "" } else { "s" },
+                    missing.join(", "),
+                    name
+                ),
+                span,
+            );
+        }
+
+        Type::Struct(name.into())
+    }
+
+    fn check_call(
+        &mut self,
+        callee: &mut Node,
+        args: &mut [Node],
+        type_args: &mut Vec<Type>,
+        span: Span,
+    ) -> Type {
+        if let Node::MemberExpr { .. } = callee {
+            return self.check_method(callee, args, span);
+        }
+        let Node::Identifier { name, .. } = callee else {
+            unreachable!("the parser only produces calls to identifiers and methods")
+        };
+        if runtime::BUILTINS.contains(&name.as_str()) {
+            return self.check_builtin(callee, args, type_args, span);
+        }
+        let Some(signature) = self.functions.get(name) else {
+            for arg in args.iter_mut() {
+                self.check_expr(arg, None);
+            }
+            return Type::Error;
+        };
+        if !signature.type_params.is_empty() {
+            return self.check_generic_call(callee, args, type_args, span);
+        }
+        let (params, return_type) = (signature.params.clone(), signature.return_type.clone());
+
+        self.check_args(&format!("function `{}`", name), &params, args, span);
+
+        return_type
+    }
+
+    fn check_enum_literal(
+        &mut self,
+        name: &str,
+        variant: &str,
+        args: &mut [Node],
+        span: Span,
+    ) -> Type {
+        let fields = match self.enums.get(name) {
+            Some(variants) => match variants.iter().find(|(other, _)| other == variant) {
+                Some((_, fields)) => Some(fields.clone()),
+                None => {
+                    self.error(
+                        "E0326",
+                        format!("no variant named `{}` in enum `{}`", variant, name),
+                        span,
+                    );
+                    None
+                }
+            },
+            None => {
+                self.error("E0301", format!("cannot find enum `{}`", name), span);
+                None
+            }
+        };
+        let Some(fields) = fields else {
+            for arg in args.iter_mut() {
+                self.check_expr(arg, None);
+            }
+            return Type::Error;
+        };
+
+        self.check_args(
+            &format!("variant `{}::{}`", name, variant),
+            &fields,
+            args,
+            span,
+        );
+        Type::Enum(name.into())
+    }
+
+    /// Checks a `match` on an enum, which has to cover every variant. As a statement the
+    /// values of the arms are discarded, otherwise they must all have the same type.
+    fn check_match(&mut self, expr: &mut Node, expected: Option<&Type>, statement: bool) -> Type {
+        let Node::Match {
+            scrutinee,
+            arms,
+            scrutinee_type,
+            resolved_type,
+            span,
+        } = expr
+        else {
+            unreachable!()
+        };
+
+        let typ = self.check_expr(scrutinee, None);
+        *scrutinee_type = Some(typ.clone());
+        let variants = match &typ {
+            Type::Enum(name) => Some(self.enums[name].clone()),
+            Type::Error => None,
+            _ => {
+                self.diagnostics.push(
+                    Diagnostic::error(
+                        "E0327",
+                        format!("cannot match on a value of type `{}`", typ),
+                        scrutinee.span(),
+                    )
+                    .with_note("`match` only works on enums"),
+                );
+                None
+            }
+        };
+
+        let mut covered: Vec<String> = vec![];
+        let mut wildcard = false;
+        let mut result_type = if statement {
+            Some(Type::Void)
+        } else {
+            expected.cloned()
+        };
+        for arm in arms.iter_mut() {
+            let Node::MatchArm { pattern, body, .. } = arm else {
+                unreachable!()
+            };
+
+            let reachable = match pattern.as_mut() {
+                Node::Wildcard { .. } => !std::mem::replace(&mut wildcard, true),
+                Node::VariantPattern {
+                    name,
+                    variant,
+                    bindings,
+                    binding_types,
+                    span,
+                } => {
+                    let fields = match &variants {
+                        None => None,
+                        Some(_) if !self.enums.contains_key(name) => {
+                            self.error("E0301", format!("cannot find enum `{}`", name), *span);
+                            None
+                        }
+                        Some(_) if Type::Enum(name.clone()) != typ => {
+                            self.error(
+                                "E0307",
+                                format!("mismatched types: expected `{}`, found `{}`", typ, name),
+                                *span,
+                            );
+                            None
+                        }
+                        Some(variants) => match variants.iter().find(|(other, _)| other == variant)
+                        {
+                            Some((_, fields)) => Some(fields.clone()),
+                            None => {
+                                self.error(
+                                    "E0326",
+                                    format!("no variant named `{}` in enum `{}`", variant, name),
+                                    *span,
+                                );
+                                None
+                            }
+                        },
+                    };
+
+                    if let Some(fields) = &fields {
+                        if fields.len() != bindings.len() {
+                            self.error(
+                                "E0306",
+                                format!(
+                                    "variant `{}::{}` has {} field{} but the pattern has {}",
+                                    name,
+                                    variant,
+                                    fields.len(),
+                                    if fields.len() == 1 { "" } else { "s" },
+                                    bindings.len()
+                                ),
+                                *span,
+                            );
+                        }
+                    }
+
+                    *binding_types = (0..bindings.len())
+                        .map(|i| {
+                            fields
+                                .as_ref()
+                                .and_then(|fields| fields.get(i).cloned())
+                                .unwrap_or(Type::Error)
+                        })
+                        .collect();
+                    for (binding, typ) in bindings.iter().zip(binding_types.iter()) {
+                        if let Node::Identifier { name, symbol, .. } = binding {
+                            if name != "_" {
+                                self.declare(*symbol, typ.clone());
+                            }
+                        }
+                    }
+
+                    match fields {
+                        // Only arms for valid variants count towards exhaustiveness.
+                        None => true,
+                        Some(_) if wildcard || covered.contains(variant) => false,
+                        Some(_) => {
+                            covered.push(variant.clone());
+                            true
+                        }
+                    }
+                }
+                _ => unreachable!("the parser only produces variant patterns and `_`"),
+            };
+            if !reachable {
+                self.diagnostics.push(
+                    Diagnostic::warning("W0301", "unreachable pattern", pattern.span())
+                        .with_note("an earlier arm already matches every value this one does"),
+                );
+            }
+
+            let arm_type = match body.as_mut() {
+                Node::Block { body, .. } => {
+                    self.check_block(body);
+                    // A block that returns never produces a value, so it fits any type.
+                    if always_returns(body) {
+                        None
+                    } else {
+                        Some(Type::Void)
+                    }
+                }
+                body => Some(self.check_expr(body, result_type.as_ref())),
+            };
+
+            match (statement, arm_type, &result_type) {
+                (true, _, _) | (false, None, _) => {}
+                (false, Some(Type::Void), _) => self.diagnostics.push(
+                    Diagnostic::error("E0308", "this arm does not produce a value", body.span())
+                        .with_help("the `match` is used as a value, so every arm needs one"),
+                ),
+                (false, Some(arm_type), Some(result_type)) => {
+                    let result_type = result_type.clone();
+                    self.expect_type(&result_type, &arm_type, body.span());
+                }
+                (false, Some(arm_type), None) => result_type = Some(arm_type),
+            }
+        }
+
+        if let Some(variants) = variants.filter(|_| !wildcard) {
+            let missing = variants
+                .iter()
+                .filter(|(variant, _)| !covered.contains(variant))
+                .map(|(variant, _)| format!("`{}::{}`", typ, variant))
+                .collect::<Vec<_>>();
+            if !missing.is_empty() {
+                self.diagnostics.push(
+                    Diagnostic::error(
+                        "E0325",
+                        format!(
+                            "non-exhaustive match: {} not covered",
+                            join_with_and(&missing)
+                        ),
+                        *span,
+                    )
+                    .with_help("add an arm for every missing variant, or a `_` arm"),
+                );
+            }
+        }
+
+        // Without a type from the context, only an arm that produces a value gives one.
+        if result_type.is_none() && typ != Type::Error && !arms.is_empty() {
+            self.diagnostics.push(
+                Diagnostic::error("E0332", "cannot infer the type of this `match`", *span)
+                    .with_note("every arm returns, so none of them gives the `match` a value")
+                    .with_help("use the `match` as a statement instead"),
+            );
+        }
+
+        let result_type = result_type.unwrap_or(Type::Error);
+        *resolved_type = Some(result_type.clone());
+        result_type
+    }
+
+    /// Infers the type arguments of a call to a generic function from its arguments, and
+    /// records them on the call; the instantiation for them is checked later.
+    fn check_generic_call(
+        &mut self,
+        callee: &mut Node,
+        args: &mut [Node],
+        call_type_args: &mut Vec<Type>,
+        span: Span,
+    ) -> Type {
+        let Node::Identifier { name, .. } = callee else {
+            unreachable!()
+        };
+        let signature = &self.functions[name.as_str()];
+        let (type_params, params, return_type) = (
+            signature.type_params.clone(),
+            signature.params.clone(),
+            signature.return_type.clone(),
+        );
+
+        let mut type_args = HashMap::new();
+        let mut arg_types = vec![];
+        for (i, arg) in args.iter_mut().enumerate() {
+            // A parameter whose type parameters are known passes its type down like any
+            // other, so that in `max(x, 1)` the `1` takes the type of `x`.
+            let expected = params
+                .get(i)
+                .map(|param| monomorphize::substitute_type(param, &type_args))
+                .filter(|param| monomorphize::type_params_of(param).is_empty());
+            let typ = self.check_expr(arg, expected.as_ref());
+            if let Some(param) = params.get(i) {
+                monomorphize::infer(param, &typ, &mut type_args);
+            }
+            arg_types.push(typ);
+        }
+
+        let params = params
+            .iter()
+            .map(|param| monomorphize::substitute_type(param, &type_args))
+            .collect::<Vec<_>>();
+        self.expect_args(
+            &format!("function `{}`", name),
+            &params,
+            &arg_types,
+            args,
+            span,
+        );
+        // Type parameters are only left unknown by arguments that already have errors.
+        if type_params
+            .iter()
+            .any(|param| !type_args.contains_key(param))
+        {
+            return Type::Error;
+        }
+
+        let described = join_with_and(
+            &type_params
+                .iter()
+                .map(|param| format!("`{} = {}`", param, type_args[param]))
+                .collect::<Vec<_>>(),
+        );
+        if type_args.values().any(|typ| *typ == Type::Void) {
+            self.diagnostics.push(
+                Diagnostic::error(
+                    "E0329",
+                    format!("`{}` cannot be called with {}", name, described),
+                    span,
+                )
+                .with_note("`void` is not the type of any value"),
+            );
+            return Type::Error;
+        }
+
+        let ordered = type_params
+            .iter()
+            .map(|param| type_args[param].clone())
+            .collect::<Vec<_>>();
+        let return_type = monomorphize::substitute_type(&return_type, &type_args);
+        // Only the instantiations of the program, which starts in ordinary functions, are made.
+        if self.generic_body {
+            return return_type;
+        }
+        let instance = (name.clone(), ordered.clone());
+        if !self.instantiated.contains(&instance) {
+            self.instantiated.push(instance);
+            if self.depth == MAX_INSTANTIATION_DEPTH {
+                self.diagnostics.push(
+                    Diagnostic::error(
+                        "E0330",
+                        format!("too many nested instantiations while calling `{}`", name),
+                        span,
+                    )
+                    .with_note(format!(
+                        "generic functions can be instantiated at most {} deep",
+                        MAX_INSTANTIATION_DEPTH
+                    ))
+                    .with_help(
+                        "a generic function cannot call itself with a type built from its own \
+                         type parameters, such as `Vec<T>`",
+                    ),
+                );
+                return Type::Error;
+            }
+
+            self.pending.push(Instance {
+                function: monomorphize::instantiate(
+                    &self.generics[name.as_str()],
+                    &type_args,
+                    ordered.clone(),
+                ),
+                params,
+                return_type: return_type.clone(),
+                type_args: described,
+                call_span: span,
+                depth: self.depth + 1,
+            });
+        }
+
+        *call_type_args = ordered;
+        return_type
+    }
+
+    /// Checks the arguments of a call against the parameter types of `callee`.
+    fn check_args(&mut self, callee: &str, params: &[Type], args: &mut [Node], span: Span) {
+        let arg_types = args
+            .iter_mut()
+            .enumerate()
+            .map(|(i, arg)| self.check_expr(arg, params.get(i)))
+            .collect::<Vec<_>>();
+
+        self.expect_args(callee, params, &arg_types, args, span);
+    }
+
+    fn expect_args(
+        &mut self,
+        callee: &str,
+        params: &[Type],
+        arg_types: &[Type],
+        args: &[Node],
+        span: Span,
+    ) {
+        if params.len() != args.len() {
+            self.diagnostics.push(
+                Diagnostic::error(
+                    "E0306",
+                    format!(
+                        "{} takes {} argument{} but {} {} supplied",
+                        callee,
+                        params.len(),
+                        if params.len() == 1 { "" } else { "s" },
+                        args.len(),
+                        if args.len() == 1 { "was" } else { "were" },
+                    ),
+                    span,
+                )
+                .with_label(format!(
+                    "expected {} argument{}",
+                    params.len(),
+                    if params.len() == 1 { "" } else { "s" }
+                )),
+            );
+        } else {
+            for ((param, arg_type), arg) in params.iter().zip(arg_types).zip(args) {
+                self.expect_type(param, arg_type, arg.span());
+            }
+        }
+    }
+
+    /// `Vec` is the only type with methods: `push(value)`, `pop()` and `len()`.
+    fn check_method(&mut self, callee: &mut Node, args: &mut [Node], span: Span) -> Type {
+        let Node::MemberExpr {
+            object,
+            property,
+            object_type: resolved_object_type,
+            ..
+        } = callee
+        else {
+            unreachable!()
+        };
+        let Node::Identifier {
+            name: method,
+            span: method_span,
+            ..
+        } = property.as_ref()
+        else {
+            unreachable!("the parser only produces identifiers as method names")
+        };
+
+        let object_type = self.check_expr(object, None);
+        *resolved_object_type = Some(object_type.clone());
+
+        let (params, return_type) = match (&object_type, method.as_str()) {
+            (Type::Error, _) => {
+                for arg in args.iter_mut() {
+                    self.check_expr(arg, None);
+                }
+                return Type::Error;
+            }
+            (Type::Vec(element), "push") => (vec![element.as_ref().clone()], Type::Void),
+            (Type::Vec(element), "pop") => (vec![], element.as_ref().clone()),
+            (Type::Vec(_), "len") => (vec![], Type::I64),
+            _ => {
+                for arg in args.iter_mut() {
+                    self.check_expr(arg, None);
+                }
+                self.error(
+                    "E0323",
+                    format!("no method named `{}` on type `{}`", method, object_type),
+                    *method_span,
+                );
+                return Type::Error;
+            }
+        };
+
+        self.check_args(&format!("method `{}`", method), &params, args, span);
+
+        return_type
+    }
+
+    /// `print` and `println` take one value of any printable type; its type is recorded as
+    /// the type argument of the call, to pick the runtime function for it.
+    fn check_builtin(
+        &mut self,
+        callee: &mut Node,
+        args: &mut [Node],
+        type_args: &mut Vec<Type>,
+        span: Span,
+    ) -> Type {
+        let Node::Identifier { name, .. } = callee else {
+            unreachable!()
+        };
+
+        let arg_type = match args {
+            [] => None,
+            [arg] => Some(self.check_expr(arg, None)),
+            _ => {
+                for arg in args.iter_mut() {
+                    self.check_expr(arg, None);
+                }
+                self.diagnostics.push(
+                    Diagnostic::error(
+                        "E0306",
+                        format!(
+                            "function `{}` takes 1 argument but {} were supplied",
+                            name,
+                            args.len()
+                        ),
+                        span,
+                    )
+                    .with_label("expected 1 argument"),
+                );
+                return Type::Void;
+            }
+        };
+        if arg_type == Some(Type::Error) {
+            return Type::Void;
+        }
+
+        match runtime::builtin_function(name, arg_type.as_ref()) {
+            Some(_) => type_args.extend(arg_type),
+            None if arg_type.is_none() => self.diagnostics.push(
+                Diagnostic::error(
+                    "E0306",
+                    format!("function `{}` takes 1 argument but 0 were supplied", name),
+                    span,
+                )
+                .with_label("expected 1 argument"),
+            ),
+            None => self.error(
+                "E0312",
+                format!("cannot print a value of type `{}`", arg_type.unwrap()),
+                args[0].span(),
+            ),
+        }
+
+        Type::Void
+    }
+}
+
+/// Whether every path through the block ends in a `return`.
+fn always_returns(body: &[Node]) -> bool {
+    body.iter().any(|stmt| match stmt {
+        Node::Return { .. } => true,
+        Node::If {
+            then_body,
+            else_body: Some(else_body),
+            ..
+        } => always_returns(then_body) && always_returns(else_body),
+        // A `match` that type checks covers every value.
+        Node::Match { arms, .. } => {
+            !arms.is_empty()
+                && arms.iter().all(|arm| {
+                    matches!(arm, Node::MatchArm { body, .. }
+                        if matches!(body.as_ref(), Node::Block { body, .. } if always_returns(body)))
+                })
+        }
+        _ => false,
+    })
+}
+
+/// `a`, `a and b`, `a, b and c`.
+fn join_with_and(items: &[String]) -> String {
+    match items {
+        [] => String::new(),
+        [item] => item.clone(),
+        [rest @ .., last] => format!("{} and {}", rest.join(", "), last),
+    }
+}
+
+/// Whether the literal was written without a type suffix, and may take the type the context expects.
+fn is_unsuffixed(typ: &str) -> bool {
+    matches!(typ, "int" | "float")
+}
+
+/// Whether the type of the expression only comes from unsuffixed literals.
+fn is_unsuffixed_expr(expr: &Node) -> bool {
+    match expr {
+        Node::NumericLiteral { typ, .. } => is_unsuffixed(typ),
+        Node::UnaryExpr {
+            operand, operator, ..
+        } => operator == "-" && is_unsuffixed_expr(operand),
+        Node::BinaryExpr {
+            left,
+            right,
+            operator,
+            ..
+        } => {
+            matches!(operator.as_str(), "+" | "-" | "*" | "/" | "%")
+                && is_unsuffixed_expr(left)
+                && is_unsuffixed_expr(right)
+        }
+        _ => false,
+    }
+}
@@ -1,4 +1,4 @@
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum TokeType {
     OpenParen,
     CloseParen,
@@ -8,6 +8,10 @@ pub enum TokeType {
     CloseBrace,
     Comma,
     Colon,
+    DoubleColon,
+    Semicolon,
+    Arrow,
+    FatArrow,
     Operator,
     Assignment,
     String,
@@ -18,45 +22,305 @@ pub enum TokeType {
     Keyword,
 }
 
+impl TokeType {
+    /// How the token kind is referred to in error messages.
+    pub fn describe(&self) -> &'static str {
+        match self {
+            TokeType::OpenParen => "`(`",
+            TokeType::CloseParen => "`)`",
+            TokeType::OpenBracket => "`[`",
+            TokeType::CloseBracket => "`]`",
+            TokeType::OpenBrace => "`{`",
+            TokeType::CloseBrace => "`}`",
+            TokeType::Comma => "`,`",
+            TokeType::Colon => "`:`",
+            TokeType::DoubleColon => "`::`",
+            TokeType::Semicolon => "`;`",
+            TokeType::Arrow => "`->`",
+            TokeType::FatArrow => "`=>`",
+            TokeType::Operator => "an operator",
+            TokeType::Assignment => "`=`",
+            TokeType::String => "a string literal",
+            TokeType::Int => "an integer literal",
+            TokeType::Float => "a float literal",
+            TokeType::Comment => "a comment",
+            TokeType::Identifier => "an identifier",
+            TokeType::Keyword => "a keyword",
+        }
+    }
+}
+
+/// A region of the source text.
+///
+/// `start` and `end` are byte offsets (end exclusive), `line` and `col` are the
+/// 1-based position of the first character.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub col: usize,
+}
+
+impl Span {
+    /// Returns a span that covers everything from the start of `self` to the end of `other`.
+    pub fn to(self, other: Span) -> Span {
+        Span {
+            end: other.end.max(self.end),
+            ..self
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Token {
     pub typ: TokeType,
     pub val: String,
+    pub span: Span,
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.typ {
+            TokeType::Operator
+            | TokeType::String
+            | TokeType::Int
+            | TokeType::Float
+            | TokeType::Comment
+            | TokeType::Identifier
+            | TokeType::Keyword => write!(f, "`{:?}({})`", self.typ, self.val),
+            _ => write!(f, "`{}`", self.val),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
 pub enum Node {
     Program {
         body: Vec<Node>,
+        span: Span,
+    },
+    /// `object.property`, or `object[property]` when `computed`.
+    MemberExpr {
+        object: Box<Node>,
+        property: Box<Node>,
+        computed: bool,
+        /// The type of `object`, filled in by the type checker for indexing and method calls,
+        /// which the C code implements differently for arrays and `Vec`s.
+        object_type: Option<Type>,
+        span: Span,
     },
-    MemberExpr,
     CallExpr {
         callee: Box<Node>,
         args: Vec<Node>,
+        /// The type arguments of the called generic function, filled in by the type checker;
+        /// `print` and `println` are generic over the type they print.
+        type_args: Vec<Type>,
+        span: Span,
     },
     NumericLiteral {
         typ: String,
         val: String,
+        span: Span,
+    },
+    BoolLiteral {
+        val: bool,
+        span: Span,
+    },
+    StringLiteral {
+        /// The contents with escape sequences already processed by the lexer.
+        val: String,
+        span: Span,
     },
     Identifier {
         name: String,
+        /// The symbol the name refers to, filled in by the resolver.
+        symbol: Option<usize>,
+        span: Span,
     },
     Variable {
         name: String,
+        /// The symbol the name refers to, filled in by the resolver.
+        symbol: Option<usize>,
+        span: Span,
+    },
+    VarDecl {
+        name: String,
+        /// `let` bindings are mutable, `const` ones are not.
+        mutable: bool,
+        type_annotation: Option<Box<Node>>, // Identifier or ArrayType
+        init: Box<Node>,
+        /// The symbol declared, filled in by the resolver.
+        symbol: Option<usize>,
+        /// The annotated or inferred type, filled in by the type checker.
+        resolved_type: Option<Type>,
+        span: Span,
+    },
+    Struct {
+        name: String,
+        fields: Vec<Node>, // TypedIdentifier
+        span: Span,
+    },
+    Enum {
+        name: String,
+        variants: Vec<Node>, // Variant
+        span: Span,
+    },
+    /// `Name(types)`, or just `Name` for a variant without fields.
+    Variant {
+        name: String,
+        fields: Vec<Node>, // Identifier or ArrayType
+        span: Span,
+    },
+    /// `Enum::Variant(args)`, or just `Enum::Variant`.
+    EnumLiteral {
+        name: String,
+        variant: String,
+        args: Vec<Node>,
+        span: Span,
+    },
+    Match {
+        scrutinee: Box<Node>,
+        arms: Vec<Node>, // MatchArm
+        /// The type of `scrutinee`, filled in by the type checker.
+        scrutinee_type: Option<Type>,
+        /// The type of the value of the `match`, `void` when it is used as a statement.
+        resolved_type: Option<Type>,
+        span: Span,
+    },
+    /// `pattern => body`, where the body is an expression or a `Block`.
+    MatchArm {
+        pattern: Box<Node>, // VariantPattern or Wildcard
+        body: Box<Node>,
+        span: Span,
+    },
+    /// `Enum::Variant(bindings)`; a binding named `_` ignores its field.
+    VariantPattern {
+        name: String,
+        variant: String,
+        bindings: Vec<Node>, // Identifier
+        /// The types of the bindings, filled in by the type checker.
+        binding_types: Vec<Type>,
+        span: Span,
+    },
+    /// `_`, which matches anything.
+    Wildcard {
+        span: Span,
+    },
+    /// `{ statements }` as the body of a match arm.
+    Block {
+        body: Vec<Node>,
+        span: Span,
+    },
+    StructLiteral {
+        name: String,
+        fields: Vec<Node>, // FieldInit
+        span: Span,
+    },
+    /// `name: value` inside a struct literal.
+    FieldInit {
+        name: String,
+        value: Box<Node>,
+        span: Span,
     },
     TypedIdentifier {
         name: String,
-        typ: Box<Node>, // Identifier
+        typ: Box<Node>, // Identifier or ArrayType
+        /// The symbol declared by a parameter, filled in by the resolver; `None` for fields.
+        symbol: Option<usize>,
+        span: Span,
+    },
+    /// `Name<args>` in type position; `Vec` is the only type that takes arguments.
+    GenericType {
+        name: String,
+        args: Vec<Node>,
+        span: Span,
+    },
+    /// `[element; len]` in type position.
+    ArrayType {
+        element: Box<Node>,
+        len: usize,
+        span: Span,
+    },
+    ArrayLiteral {
+        elements: Vec<Node>,
+        /// Filled in by the type checker, the C code needs to name the array type.
+        resolved_type: Option<Type>,
+        span: Span,
     },
     BinaryExpr {
         left: Box<Node>,
         right: Box<Node>,
         operator: String,
+        /// The type of the result, filled in by the type checker; C computes narrow integer
+        /// arithmetic in `int`, so the result has to be converted back to it.
+        resolved_type: Option<Type>,
+        span: Span,
+    },
+    UnaryExpr {
+        operand: Box<Node>,
+        operator: String,
+        /// The type of the result, filled in by the type checker.
+        resolved_type: Option<Type>,
+        span: Span,
+    },
+    Cast {
+        expr: Box<Node>,
+        typ: Box<Node>, // Identifier or ArrayType
+        span: Span,
+    },
+    If {
+        condition: Box<Node>,
+        then_body: Vec<Node>,
+        /// `else if` chains are stored as an `else` block holding a single `If`.
+        else_body: Option<Vec<Node>>,
+        span: Span,
+    },
+    While {
+        condition: Box<Node>,
+        body: Vec<Node>,
+        span: Span,
+    },
+    For {
+        var: String,
+        iter: Box<Node>, // Range or any expression of type `Vec`
+        /// The symbol declared by `var`, filled in by the resolver.
+        symbol: Option<usize>,
+        /// The type of `iter`, filled in by the type checker.
+        iter_type: Option<Type>,
+        body: Vec<Node>,
+        span: Span,
+    },
+    /// `start..end`, only valid as the iterable of a `for` loop.
+    Range {
+        start: Box<Node>,
+        end: Box<Node>,
+        span: Span,
+    },
+    Break {
+        /// The loop to leave, when a `match` is in between: in C, `break` would only leave
+        /// the `switch` the `match` turns into.
+        loop_span: Option<Span>,
+        span: Span,
+    },
+    Continue {
+        span: Span,
+    },
+    Return {
+        value: Option<Box<Node>>,
+        span: Span,
     },
     Function {
         name: String,
+        /// The `T` in `fn max<T>(...)`, as `Identifier`s; empty for ordinary functions.
+        type_params: Vec<Node>,
+        /// The type arguments of an instantiation of a generic function, filled in by the
+        /// type checker; empty for ordinary functions.
+        type_args: Vec<Type>,
         params: Vec<Node>,
+        return_type: Option<Box<Node>>, // `None` for functions that return nothing
         body: Vec<Node>,
+        span: Span,
     },
 }
 
@@ -67,6 +331,345 @@ impl Node {
             _ => None,
         }
     }
+
+    /// The nodes directly inside this one, including the nodes of type annotations.
+    pub fn children(&self) -> Vec<&Node> {
+        match self {
+            Node::Program { body, .. }
+            | Node::Struct { fields: body, .. }
+            | Node::Block { body, .. } => body.iter().collect(),
+            Node::StructLiteral { fields: nodes, .. }
+            | Node::ArrayLiteral {
+                elements: nodes, ..
+            }
+            | Node::Enum {
+                variants: nodes, ..
+            }
+            | Node::Variant { fields: nodes, .. }
+            | Node::EnumLiteral { args: nodes, .. }
+            | Node::VariantPattern {
+                bindings: nodes, ..
+            } => nodes.iter().collect(),
+            Node::Match {
+                scrutinee, arms, ..
+            } => std::iter::once(scrutinee.as_ref()).chain(arms).collect(),
+            Node::MatchArm { pattern, body, .. } => vec![pattern, body],
+            Node::MemberExpr {
+                object, property, ..
+            } => vec![object, property],
+            Node::CallExpr { callee, args, .. } => {
+                std::iter::once(callee.as_ref()).chain(args).collect()
+            }
+            Node::VarDecl {
+                type_annotation,
+                init,
+                ..
+            } => type_annotation
+                .iter()
+                .map(Box::as_ref)
+                .chain([init.as_ref()])
+                .collect(),
+            Node::FieldInit { value, .. } => vec![value],
+            Node::TypedIdentifier { typ, .. } => vec![typ],
+            Node::ArrayType { element, .. } => vec![element],
+            Node::GenericType { args, .. } => args.iter().collect(),
+            Node::BinaryExpr { left, right, .. } => vec![left, right],
+            Node::UnaryExpr { operand, .. } => vec![operand],
+            Node::Cast { expr, typ, .. } => vec![expr, typ],
+            Node::If {
+                condition,
+                then_body,
+                else_body,
+                ..
+            } => std::iter::once(condition.as_ref())
+                .chain(then_body)
+                .chain(else_body.iter().flatten())
+                .collect(),
+            Node::While {
+                condition, body, ..
+            } => std::iter::once(condition.as_ref()).chain(body).collect(),
+            Node::For { iter, body, .. } => std::iter::once(iter.as_ref()).chain(body).collect(),
+            Node::Range { start, end, .. } => vec![start, end],
+            Node::Return { value, .. } => value.iter().map(Box::as_ref).collect(),
+            Node::Function {
+                params,
+                return_type,
+                body,
+                ..
+            } => params
+                .iter()
+                .chain(return_type.iter().map(Box::as_ref))
+                .chain(body)
+                .collect(),
+            Node::NumericLiteral { .. }
+            | Node::BoolLiteral { .. }
+            | Node::StringLiteral { .. }
+            | Node::Identifier { .. }
+            | Node::Variable { .. }
+            | Node::Wildcard { .. }
+            | Node::Break { .. }
+            | Node::Continue { .. } => vec![],
+        }
+    }
+
+    /// Like `children`, but for rewriting the nodes in place.
+    pub fn children_mut(&mut self) -> Vec<&mut Node> {
+        match self {
+            Node::Program { body, .. }
+            | Node::Struct { fields: body, .. }
+            | Node::Block { body, .. } => body.iter_mut().collect(),
+            Node::StructLiteral { fields: nodes, .. }
+            | Node::ArrayLiteral {
+                elements: nodes, ..
+            }
+            | Node::Enum {
+                variants: nodes, ..
+            }
+            | Node::Variant { fields: nodes, .. }
+            | Node::EnumLiteral { args: nodes, .. }
+            | Node::VariantPattern {
+                bindings: nodes, ..
+            } => nodes.iter_mut().collect(),
+            Node::Match {
+                scrutinee, arms, ..
+            } => std::iter::once(scrutinee.as_mut()).chain(arms).collect(),
+            Node::MatchArm { pattern, body, .. } => vec![pattern, body],
+            Node::MemberExpr {
+                object, property, ..
+            } => vec![object, property],
+            Node::CallExpr { callee, args, .. } => {
+                std::iter::once(callee.as_mut()).chain(args).collect()
+            }
+            Node::VarDecl {
+                type_annotation,
+                init,
+                ..
+            } => type_annotation
+                .iter_mut()
+                .map(Box::as_mut)
+                .chain([init.as_mut()])
+                .collect(),
+            Node::FieldInit { value, .. } => vec![value],
+            Node::TypedIdentifier { typ, .. } => vec![typ],
+            Node::ArrayType { element, .. } => vec![element],
+            Node::GenericType { args, .. } => args.iter_mut().collect(),
+            Node::BinaryExpr { left, right, .. } => vec![left, right],
+            Node::UnaryExpr { operand, .. } => vec![operand],
+            Node::Cast { expr, typ, .. } => vec![expr, typ],
+            Node::If {
+                condition,
+                then_body,
+                else_body,
+                ..
+            } => std::iter::once(condition.as_mut())
+                .chain(then_body)
+                .chain(else_body.iter_mut().flatten())
+                .collect(),
+            Node::While {
+                condition, body, ..
+            } => std::iter::once(condition.as_mut()).chain(body).collect(),
+            Node::For { iter, body, .. } => std::iter::once(iter.as_mut()).chain(body).collect(),
+            Node::Range { start, end, .. } => vec![start, end],
+            Node::Return { value, .. } => value.iter_mut().map(Box::as_mut).collect(),
+            Node::Function {
+                params,
+                return_type,
+                body,
+                ..
+            } => params
+                .iter_mut()
+                .chain(return_type.iter_mut().map(Box::as_mut))
+                .chain(body)
+                .collect(),
+            Node::NumericLiteral { .. }
+            | Node::BoolLiteral { .. }
+            | Node::StringLiteral { .. }
+            | Node::Identifier { .. }
+            | Node::Variable { .. }
+            | Node::Wildcard { .. }
+            | Node::Break { .. }
+            | Node::Continue { .. } => vec![],
+        }
+    }
+
+    pub fn span(&self) -> Span {
+        match self {
+            Node::Program { span, .. }
+            | Node::CallExpr { span, .. }
+            | Node::NumericLiteral { span, .. }
+            | Node::StringLiteral { span, .. }
+            | Node::BoolLiteral { span, .. }
+            | Node::Identifier { span, .. }
+            | Node::Variable { span, .. }
+            | Node::VarDecl { span, .. }
+            | Node::TypedIdentifier { span, .. }
+            | Node::ArrayType { span, .. }
+            | Node::GenericType { span, .. }
+            | Node::ArrayLiteral { span, .. }
+            | Node::MemberExpr { span, .. }
+            | Node::Struct { span, .. }
+            | Node::StructLiteral { span, .. }
+            | Node::FieldInit { span, .. }
+            | Node::BinaryExpr { span, .. }
+            | Node::UnaryExpr { span, .. }
+            | Node::Cast { span, .. }
+            | Node::If { span, .. }
+            | Node::While { span, .. }
+            | Node::For { span, .. }
+            | Node::Range { span, .. }
+            | Node::Enum { span, .. }
+            | Node::Variant { span, .. }
+            | Node::EnumLiteral { span, .. }
+            | Node::Match { span, .. }
+            | Node::MatchArm { span, .. }
+            | Node::VariantPattern { span, .. }
+            | Node::Wildcard { span }
+            | Node::Block { span, .. }
+            | Node::Break { span, .. }
+            | Node::Continue { span }
+            | Node::Return { span, .. }
+            | Node::Function { span, .. } => *span,
+        }
+    }
+}
+
+/// The type of a value, as seen by the type checker.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Type {
+    I8,
+    I16,
+    I32,
+    I64,
+    U8,
+    U16,
+    U32,
+    U64,
+    F32,
+    F64,
+    Bool,
+    Str,
+    /// A struct declared in the program, by name.
+    Struct(String),
+    /// An enum declared in the program, by name.
+    Enum(String),
+    /// A fixed-size array, `[element; len]`.
+    Array(Box<Type>, usize),
+    /// A growable list on the heap, `Vec<element>`. Copies share the same elements.
+    Vec(Box<Type>),
+    /// A type parameter of a generic function; only found in the signatures of generic
+    /// functions, since their bodies are checked once per instantiation.
+    Param(String),
+    Void,
+    /// The type of an expression that already produced an error; never reported again.
+    Error,
+}
+
+impl Type {
+    /// Looks up one of the builtin type names; `int` and `float` are aliases of `i64` and `f64`.
+    pub fn from_name(name: &str) -> Option<Type> {
+        match name {
+            "i8" => Some(Type::I8),
+            "i16" => Some(Type::I16),
+            "i32" => Some(Type::I32),
+            "int" | "i64" => Some(Type::I64),
+            "u8" => Some(Type::U8),
+            "u16" => Some(Type::U16),
+            "u32" => Some(Type::U32),
+            "u64" => Some(Type::U64),
+            "f32" => Some(Type::F32),
+            "float" | "f64" => Some(Type::F64),
+            "bool" => Some(Type::Bool),
+            "string" => Some(Type::Str),
+            "void" => Some(Type::Void),
+            _ => None,
+        }
+    }
+
+    pub fn is_numeric(&self) -> bool {
+        self.is_integer() || self.is_float()
+    }
+
+    pub fn is_integer(&self) -> bool {
+        self.is_signed() || self.is_unsigned()
+    }
+
+    pub fn is_signed(&self) -> bool {
+        matches!(self, Type::I8 | Type::I16 | Type::I32 | Type::I64)
+    }
+
+    pub fn is_unsigned(&self) -> bool {
+        matches!(self, Type::U8 | Type::U16 | Type::U32 | Type::U64)
+    }
+
+    pub fn is_float(&self) -> bool {
+        matches!(self, Type::F32 | Type::F64)
+    }
+
+    /// The width of a numeric type in bits, 0 for everything else.
+    pub fn bits(&self) -> u32 {
+        match self {
+            Type::I8 | Type::U8 => 8,
+            Type::I16 | Type::U16 => 16,
+            Type::I32 | Type::U32 | Type::F32 => 32,
+            Type::I64 | Type::U64 | Type::F64 => 64,
+            _ => 0,
+        }
+    }
+
+    /// Whether every value of this type can be converted to `other` without loss,
+    /// which is the only conversion that happens without an explicit cast.
+    pub fn widens_to(&self, other: &Type) -> bool {
+        if self == other {
+            return true;
+        }
+
+        let same_kind = (self.is_float() && other.is_float())
+            || (self.is_signed() && other.is_signed())
+            || (self.is_unsigned() && other.is_unsigned())
+            // An unsigned value fits into a wider signed type, which has a spare bit for the sign.
+            || (self.is_unsigned() && other.is_signed());
+
+        same_kind && self.bits() < other.bits()
+    }
+
+    /// The largest value of an integer type.
+    pub fn max_value(&self) -> u64 {
+        match self {
+            Type::I8 => i8::MAX as u64,
+            Type::I16 => i16::MAX as u64,
+            Type::I32 => i32::MAX as u64,
+            Type::I64 => i64::MAX as u64,
+            Type::U8 => u8::MAX as u64,
+            Type::U16 => u16::MAX as u64,
+            Type::U32 => u32::MAX as u64,
+            Type::U64 => u64::MAX,
+            _ => unreachable!("{} is not an integer type", self),
+        }
+    }
+}
+
+impl std::fmt::Display for Type {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Type::I8 => write!(f, "i8"),
+            Type::I16 => write!(f, "i16"),
+            Type::I32 => write!(f, "i32"),
+            Type::I64 => write!(f, "i64"),
+            Type::U8 => write!(f, "u8"),
+            Type::U16 => write!(f, "u16"),
+            Type::U32 => write!(f, "u32"),
+            Type::U64 => write!(f, "u64"),
+            Type::F32 => write!(f, "f32"),
+            Type::F64 => write!(f, "f64"),
+            Type::Bool => write!(f, "bool"),
+            Type::Str => write!(f, "string"),
+            Type::Struct(name) | Type::Enum(name) | Type::Param(name) => write!(f, "{}", name),
+            Type::Array(element, len) => write!(f, "[{}; {}]", element, len),
+            Type::Vec(element) => write!(f, "Vec<{}>", element),
+            Type::Void => write!(f, "void"),
+            Type::Error => write!(f, "{{error}}"),
+        }
+    }
 }
 
 pub struct Statement {
@@ -0,0 +1,1452 @@
+//! End-to-end tests: compile arlang programs to C and build them with the local C compiler.
+
+use std::path::PathBuf;
+use std::process::Command;
+
+/// Compiles `source` with arlang and then with `cc`, returning the path of the executable,
+/// or `None` when no C compiler is installed.
+fn build(name: &str, source: &str) -> Option<PathBuf> {
+    if Command::new("cc").arg("--version").output().is_err() {
+        eprintln!("skipping {}: no C compiler found", name);
+        return None;
+    }
+
+    let dir = std::env::temp_dir().join(format!("arlang-test-{}-{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let input = dir.join(format!("{}.ar", name));
+    let c_file = dir.join(format!("{}.c", name));
+    let exe = dir.join(name);
+    std::fs::write(&input, source).unwrap();
+
+    let arlang = Command::new(env!("CARGO_BIN_EXE_arlang"))
+        .arg("build")
+        .arg(&input)
+        .arg("-o")
+        .arg(&c_file)
+        .output()
+        .unwrap();
+    assert!(
+        arlang.status.success(),
+        "arlang failed:\n{}",
+        String::from_utf8_lossy(&arlang.stderr)
+    );
+
+    let cc = Command::new("cc")
+        .args(["-std=c11", "-pedantic-errors", "-Wall", "-Werror", "-o"])
+        .arg(&exe)
+        .arg(&c_file)
+        .output()
+        .unwrap();
+    assert!(
+        cc.status.success(),
+        "cc rejected the generated code:\n{}\n{}",
+        String::from_utf8_lossy(&cc.stderr),
+        std::fs::read_to_string(&c_file).unwrap()
+    );
+
+    Some(exe)
+}
+
+fn run(exe: PathBuf) -> String {
+    let output = Command::new(exe).output().unwrap();
+    assert!(output.status.success());
+    String::from_utf8(output.stdout).unwrap()
+}
+
+/// Compiles `source` with arlang, which must reject it, and returns the diagnostics it printed.
+fn errors(name: &str, source: &str) -> String {
+    let dir = std::env::temp_dir().join(format!("arlang-test-{}-{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let input = dir.join(format!("{}.ar", name));
+    std::fs::write(&input, source).unwrap();
+
+    let arlang = Command::new(env!("CARGO_BIN_EXE_arlang"))
+        .arg("build")
+        .arg(&input)
+        .arg("-o")
+        .arg(dir.join(format!("{}.c", name)))
+        .env("NO_COLOR", "1")
+        .output()
+        .unwrap();
+    assert!(!arlang.status.success(), "arlang accepted {}", name);
+    String::from_utf8(arlang.stderr).unwrap()
+}
+
+#[test]
+fn functions_with_parameters() {
+    let source = r#"
+fn add(a: int, b: int) -> int {
+    return a + b
+}
+
+fn mean(a: float, b: float) -> float {
+    return (a + b) / 2.0
+}
+
+fn main() {
+    for i in 0..add(1, 2) {
+        if mean(1.0, 2.0) > 1.0 {
+            continue
+        }
+    }
+}
+"#;
+
+    if let Some(exe) = build("functions_with_parameters", source) {
+        run(exe);
+    }
+}
+
+#[test]
+fn printing() {
+    let source = r#"
+fn main() {
+    let name = "arlang"
+    print("hello, ")
+    println(name)
+    println(40 + 2)
+    println(1.5)
+    println()
+    println("tab\tquote\" backslash\\ ??=")
+}
+"#;
+
+    if let Some(exe) = build("printing", source) {
+        assert_eq!(
+            run(exe),
+            "hello, arlang\n42\n1.5\n\ntab\tquote\" backslash\\ ??=\n"
+        );
+    }
+}
+
+#[test]
+fn booleans() {
+    let source = r#"
+fn is_even(n: int) -> bool {
+    return n % 2 == 0
+}
+
+fn main() {
+    let done = false
+    println(done)
+    println(is_even(4) && !done)
+    println(1 < 2 == true)
+    if is_even(3) || done {
+        println("unreachable")
+    } else {
+        println("odd")
+    }
+}
+"#;
+
+    if let Some(exe) = build("booleans", source) {
+        assert_eq!(run(exe), "false\ntrue\ntrue\nodd\n");
+    }
+}
+
+#[test]
+fn operators() {
+    let source = r#"
+fn yes(label: string) -> bool {
+    println(label)
+    return true
+}
+
+fn main() {
+    let a = 1
+    let b = 2
+    println(a < b)
+    println(a > b)
+    println(a <= 1)
+    println(b >= 3)
+    println(a == 1)
+    println(a != 1)
+    println(!(a == 1))
+    println(-a + b)
+    println(-(a - b) * 3)
+    println(true || false && false)
+    println(1 + 2 * 3 == 7)
+    println(1.5 < 2.5)
+    println(false && yes("and"))
+    println(true || yes("or"))
+    println(true && yes("right"))
+}
+"#;
+
+    if let Some(exe) = build("operators", source) {
+        assert_eq!(
+            run(exe),
+            "true\nfalse\ntrue\nfalse\ntrue\nfalse\nfalse\n1\n3\ntrue\ntrue\ntrue\nfalse\ntrue\nright\ntrue\n"
+        );
+    }
+
+    let source = r#"
+fn main() {
+    let a = !1
+    let b = 1 && true
+}
+"#;
+
+    let stderr = errors("operator_errors", source);
+    assert!(stderr.contains("error[E0305]: cannot apply unary `!` to `i64`\n"));
+    assert!(stderr.contains("3 |     let a = !1\n  |             ^^\n"));
+    assert!(stderr.contains("error[E0305]: cannot apply `&&` to `i64` and `bool`\n"));
+    assert!(stderr.contains("4 |     let b = 1 && true\n  |             ^^^^^^^^^\n"));
+}
+
+#[test]
+fn conditionals() {
+    let source = r#"
+fn classify(n: int) -> string {
+    if n < 0 {
+        return "negative"
+    } else if n == 0 {
+        return "zero"
+    } else if n < 10 {
+        return "small"
+    } else {
+        return "large"
+    }
+}
+
+fn main() {
+    println(classify(-3))
+    println(classify(0))
+    println(classify(7))
+    println(classify(42))
+    if true {
+        println("then")
+    }
+    if false {
+        println("unreachable")
+    }
+    let x = 5
+    if x > 3 {
+        let y = x * 2
+        println(y)
+    } else {
+        println("no")
+    }
+}
+"#;
+
+    if let Some(exe) = build("conditionals", source) {
+        assert_eq!(run(exe), "negative\nzero\nsmall\nlarge\nthen\n10\n");
+    }
+
+    let source = r#"
+fn main() {
+    if 1 {
+        println(1)
+    }
+}
+"#;
+
+    let stderr = errors("condition_errors", source);
+    assert!(stderr.contains("error[E0307]: mismatched types: expected `bool`, found `i64`\n"));
+    assert!(stderr.contains("3 |     if 1 {\n  |        ^ expected `bool`\n"));
+}
+
+#[test]
+fn loops() {
+    let source = r#"
+fn main() {
+    let n = 0
+    while n < 3 {
+        print(n)
+        n = n + 1
+    }
+    println()
+    for i in 0..5 {
+        if i == 1 {
+            continue
+        }
+        if i == 4 {
+            break
+        }
+        print(i)
+    }
+    println()
+    let total = 0
+    for i in 1..4 {
+        for j in 0..i {
+            total = total + j
+        }
+    }
+    println(total)
+    for i in 3..3 {
+        println("empty")
+    }
+    while true {
+        break
+    }
+    println("done")
+}
+"#;
+
+    if let Some(exe) = build("loops", source) {
+        assert_eq!(run(exe), "012\n023\n4\ndone\n");
+    }
+
+    let source = r#"
+fn main() {
+    break
+}
+"#;
+
+    let stderr = errors("loop_errors", source);
+    assert!(stderr.contains("error[E0107]: `break` outside of a loop\n"));
+    assert!(stderr.contains("3 |     break\n  |     ^^^^^ cannot `break` outside of a loop\n"));
+}
+
+#[test]
+fn local_declarations() {
+    let source = r#"
+fn main() {
+    const greeting = "hi"
+    let count: int = 1
+    let ratio: float = 0.5
+    let small: i32 = 7
+    let flag: bool = false
+    count = count + 41
+    ratio = ratio * 3.0
+    flag = !flag
+    println(greeting)
+    println(count)
+    println(ratio)
+    println(small)
+    println(flag)
+}
+"#;
+
+    if let Some(exe) = build("local_declarations", source) {
+        assert_eq!(run(exe), "hi\n42\n1.5\n7\ntrue\n");
+    }
+
+    let source = r#"
+fn main() {
+    const limit: int = 10
+    limit = 11
+    let name: string = 5
+}
+"#;
+
+    let stderr = errors("declaration_errors", source);
+    assert!(stderr.contains("error[E0205]: cannot assign to constant `limit`\n"));
+    assert!(stderr.contains("4 |     limit = 11\n  |     ^^^^^\n"));
+    assert!(stderr.contains("= note: `limit` is declared at line 3, column 5\n"));
+    assert!(stderr.contains("error[E0307]: mismatched types: expected `string`, found `i64`\n"));
+    assert!(stderr.contains(
+        "5 |     let name: string = 5\n  |                        ^ expected `string`\n"
+    ));
+}
+
+#[test]
+fn type_inference() {
+    let source = r#"
+fn half(x: f32) -> f32 {
+    return x / 2.0
+}
+
+fn main() {
+    let a = 1 + 2
+    let b = 2.0 * 1.25
+    let c = a > 2
+    let d = half(3.0)
+    let e = a as float + b
+    let f = "text"
+    let g = d
+    println(a / 2)
+    println(b)
+    println(c)
+    println(g)
+    println(e)
+    println(f)
+}
+"#;
+
+    if let Some(exe) = build("type_inference", source) {
+        assert_eq!(run(exe), "1\n2.5\ntrue\n1.5\n5.5\ntext\n");
+    }
+
+    let source = r#"
+fn main() {
+    let x = 1 + 2.0
+    let y = 3
+    y = 1.5
+}
+"#;
+
+    let stderr = errors("inference_errors", source);
+    assert!(stderr.contains("error[E0305]: cannot apply `+` to `i64` and `f64`\n"));
+    assert!(stderr.contains("3 |     let x = 1 + 2.0\n  |             ^^^^^^^\n"));
+    assert!(stderr.contains("= help: use `1.0` to write a float literal\n"));
+    assert!(stderr.contains("error[E0307]: mismatched types: expected `i64`, found `f64`\n"));
+    assert!(stderr.contains("5 |     y = 1.5\n  |         ^^^ expected `i64`\n"));
+}
+
+#[test]
+fn number_literals() {
+    let source = r#"
+fn main() {
+    println(0xFF)
+    println(0b1010 + 0o17)
+    println(1_000_000)
+    println(1.5e-3)
+    println(2.5f64 * 2.0)
+    for i in 1..3 {
+        print(i)
+    }
+    println()
+}
+"#;
+
+    if let Some(exe) = build("number_literals", source) {
+        assert_eq!(run(exe), "255\n25\n1000000\n0.0015\n5\n12\n");
+    }
+}
+
+#[test]
+fn sized_numbers() {
+    let source = r#"
+fn widen(x: i32) -> i64 {
+    return x
+}
+
+fn main() {
+    let small: u8 = 255
+    let min: i8 = -128
+    let big: u64 = 18446744073709551615
+    let half: f32 = 0.5
+    println(small)
+    println(min)
+    println(big)
+    println(widen(-7i32) + small)
+    println(half + 1.0)
+}
+"#;
+
+    if let Some(exe) = build("sized_numbers", source) {
+        assert_eq!(run(exe), "255\n-128\n18446744073709551615\n248\n1.5\n");
+    }
+}
+
+#[test]
+fn casts() {
+    let source = r#"
+fn main() {
+    let big: i64 = 300
+    let x: f64 = 2.75
+    println(big as u8)
+    println(-x as i32 * 2)
+    println((big as f32) / 8.0)
+    println(true as u8 + 1)
+}
+"#;
+
+    if let Some(exe) = build("casts", source) {
+        assert_eq!(run(exe), "44\n-4\n37.5\n2\n");
+    }
+}
+
+#[test]
+fn structs() {
+    let source = r#"
+struct Line { from: Point, to: Point }
+
+struct Point {
+    x: f64,
+    y: f64,
+}
+
+fn length_squared(line: Line) -> f64 {
+    let dx = line.to.x - line.from.x
+    let dy = line.to.y - line.from.y
+    return dx * dx + dy * dy
+}
+
+fn origin() -> Point {
+    return Point { x: 0.0, y: 0.0 }
+}
+
+fn main() {
+    let p = Point { x: 3.0, y: 4.0 }
+    let line = Line { from: origin(), to: p }
+    println(length_squared(line))
+    line.to.x = 6.0
+    line.from.y += 1.0
+    println(line.to.x + line.from.y)
+    if p.x < 4.0 {
+        println(origin().y)
+    }
+}
+"#;
+
+    if let Some(exe) = build("structs", source) {
+        assert_eq!(run(exe), "25\n7\n0\n");
+    }
+}
+
+#[test]
+fn arrays() {
+    let source = r#"
+struct Polygon {
+    corners: [[i32; 2]; 3],
+}
+
+fn sum(values: [int; 4]) -> int {
+    let total = 0
+    for i in 0..4 {
+        total += values[i]
+    }
+    return total
+}
+
+fn main() {
+    let values = [1, 2, 3, 4]
+    values[0] = 10
+    println(sum(values))
+
+    let shape = Polygon { corners: [[0, 0], [4, 0], [0, 3]] }
+    shape.corners[1][0] += 1
+    println(shape.corners[1][0] * shape.corners[2][1])
+
+    const flags: [bool; 2] = [true, false]
+    println(flags[1])
+}
+"#;
+
+    if let Some(exe) = build("arrays", source) {
+        assert_eq!(run(exe), "19\n15\nfalse\n");
+    }
+}
+
+#[test]
+fn array_bounds_checks() {
+    let source = r#"
+fn main() {
+    let values = [1, 2, 3]
+    let i = 3
+    println(values[i])
+}
+"#;
+
+    if let Some(exe) = build("array_bounds_checks", source) {
+        let output = Command::new(exe).output().unwrap();
+        assert_eq!(output.status.code(), Some(101));
+        assert_eq!(
+            String::from_utf8_lossy(&output.stderr),
+            "index out of bounds: the length is 3 but the index is 3\n"
+        );
+    }
+}
+
+#[test]
+fn vecs() {
+    let source = r#"
+struct Tree {
+    value: int,
+    children: Vec<Tree>,
+}
+
+fn total(tree: Tree) -> int {
+    let sum = tree.value
+    for child in tree.children {
+        sum += total(child)
+    }
+    return sum
+}
+
+fn main() {
+    let squares: Vec<int> = []
+    for i in 0..10 {
+        squares.push(i * i)
+    }
+    println(squares.len())
+    println(squares.pop())
+    squares[0] = 7
+    println(squares[0] + squares[8])
+
+    let words: Vec<string> = ["a", "b"]
+    words.push("c")
+    for word in words {
+        print(word)
+    }
+    println()
+
+    let rows: Vec<Vec<u8>> = [[1], []]
+    rows[1].push(5)
+    println(rows[1][0])
+
+    const leaf = Tree { value: 2, children: [] }
+    println(total(Tree { value: 1, children: [leaf, leaf] }))
+}
+"#;
+
+    if let Some(exe) = build("vecs", source) {
+        assert_eq!(run(exe), "10\n81\n71\nabc\n5\n5\n");
+    }
+}
+
+#[test]
+fn enums() {
+    let source = r#"
+enum Shape {
+    Circle(f64),
+    Rect(f64, f64),
+    Empty,
+}
+
+enum Expr {
+    Num(int),
+    Add(Vec<Expr>),
+}
+
+fn eval(expr: Expr) -> int {
+    match expr {
+        Expr::Num(n) => {
+            return n
+        }
+        Expr::Add(terms) => {
+            let sum = 0
+            for term in terms {
+                sum += eval(term)
+            }
+            return sum
+        }
+    }
+}
+
+fn area(shape: Shape) -> f64 {
+    return match shape {
+        Shape::Circle(r) => 3.0 * r * r,
+        Shape::Rect(w, h) => w * h,
+        Shape::Empty => 0.0,
+    }
+}
+
+fn main() {
+    let shapes: Vec<Shape> = [Shape::Circle(1.0), Shape::Rect(2.0, 3.5), Shape::Empty]
+    for shape in shapes {
+        println(area(shape))
+    }
+    const e = Expr::Add([Expr::Num(1), Expr::Num(2), Expr::Add([Expr::Num(3)])])
+    println(eval(e))
+
+    let i = 0
+    while true {
+        match shapes[i] {
+            Shape::Empty => {
+                break
+            }
+            _ => {
+                i += 1
+                continue
+            }
+        }
+    }
+    println(i)
+    match shapes[0] {
+        Shape::Circle(_) => println("circle"),
+        _ => {}
+    }
+}
+"#;
+
+    if let Some(exe) = build("enums", source) {
+        assert_eq!(run(exe), "3\n7\n0\n6\n2\ncircle\n");
+    }
+}
+
+#[test]
+fn generics() {
+    let source = r#"
+struct Point {
+    x: int,
+    y: int,
+}
+
+fn max<T>(a: T, b: T) -> T {
+    if a > b {
+        return a
+    }
+    return b
+}
+
+fn pick<T>(first: bool, a: T, b: T) -> T {
+    if first {
+        return a
+    }
+    return b
+}
+
+fn last<T>(items: Vec<T>) -> T {
+    return items[items.len() - 1]
+}
+
+fn second<A, B>(a: A, b: B) -> B {
+    const copy: A = a
+    print(copy)
+    print(" ")
+    return b
+}
+
+fn countdown<T>(n: T) -> T {
+    if n == 0 {
+        return n
+    }
+    return countdown(n - 1)
+}
+
+fn main() {
+    println(max(3, 7))
+    println(max(2.5, 1.5))
+    const small: u8 = 4
+    println(max(small, 9))
+    println(pick(false, Point { x: 1, y: 2 }, Point { x: 3, y: 4 }).y)
+    const words: Vec<string> = ["a", "b"]
+    println(last(words))
+    println(second(1, "two"))
+    println(countdown(5))
+}
+"#;
+
+    if let Some(exe) = build("generics", source) {
+        assert_eq!(run(exe), "7\n2.5\n9\n4\nb\n1 two\n0\n");
+    }
+}
+
+#[test]
+fn narrow_integers_wrap() {
+    let source = r#"
+fn main() {
+    let x: u8 = 255
+    println(x + 1u8)
+    const a: u8 = 200
+    const b: u8 = 100
+    println(a + b > 255)
+    let y: i8 = 127
+    println(y + 1)
+    println(-128i8)
+    let z: u16 = 65535
+    println(z * 2)
+    let w: i16 = -32768
+    println(-w)
+    println(-9223372036854775808)
+}
+"#;
+
+    if let Some(exe) = build("narrow_integers_wrap", source) {
+        assert_eq!(
+            run(exe),
+            "0\nfalse\n-128\n-128\n65534\n-32768\n-9223372036854775808\n"
+        );
+    }
+}
+
+#[test]
+fn c_names_are_not_reserved() {
+    let source = r#"
+struct FILE {
+    int: int,
+}
+
+enum size_t {
+    double(int),
+    char,
+}
+
+fn abort() {
+    println("abort")
+}
+
+fn puts(char: int) -> int {
+    return char + 1
+}
+
+fn main() {
+    let double = 1
+    const printf = 2
+    const f = FILE { int: double + printf }
+    abort()
+    println(puts(f.int))
+    match size_t::double(4) {
+        size_t::double(unsigned) => println(unsigned),
+        size_t::char => {}
+    }
+}
+"#;
+
+    if let Some(exe) = build("c_names_are_not_reserved", source) {
+        assert_eq!(run(exe), "abort\n4\n4\n");
+    }
+}
+
+#[test]
+fn hidden_loop_variables() {
+    let source = r#"
+fn main() {
+    let n__end = 100
+    for n in 0..2 {
+        for m in 0..n__end - 99 {
+            println(n__end + n + m)
+        }
+    }
+}
+"#;
+
+    if let Some(exe) = build("hidden_loop_variables", source) {
+        assert_eq!(run(exe), "100\n101\n");
+    }
+}
+
+#[test]
+fn hidden_vec_loop_variables() {
+    let source = r#"
+fn main() {
+    const x__vec: Vec<int> = [7]
+    const x__index = 5
+    const rows: Vec<Vec<int>> = [[1, 2], [3]]
+    for x in rows {
+        for y in x {
+            println(x__index + y + x__vec[0])
+        }
+    }
+}
+"#;
+
+    if let Some(exe) = build("hidden_vec_loop_variables", source) {
+        assert_eq!(run(exe), "13\n14\n15\n");
+    }
+}
+
+#[test]
+fn hidden_match_variables() {
+    let source = r#"
+enum Choice {
+    Yes(int),
+    No,
+}
+
+fn main() {
+    const arlang_match = 7
+    const arlang_result = "result"
+    const choice = Choice::Yes(1)
+    match choice {
+        Choice::Yes(n) => println(arlang_match + n),
+        Choice::No => {}
+    }
+    const text = match choice {
+        Choice::Yes(_) => arlang_result,
+        Choice::No => "no",
+    }
+    println(text)
+}
+"#;
+
+    if let Some(exe) = build("hidden_match_variables", source) {
+        assert_eq!(run(exe), "8\nresult\n");
+    }
+}
+
+#[test]
+fn assignment_through_temporaries() {
+    let source = r#"
+struct Point {
+    x: int,
+}
+
+fn origin() -> Point {
+    return Point { x: 0 }
+}
+
+fn empty() -> Vec<int> {
+    const items: Vec<int> = []
+    return items
+}
+
+fn main() {
+    origin().x = 1
+    empty().push(1)
+}
+"#;
+
+    let stderr = errors("assignment_through_temporaries", source);
+    assert!(stderr.contains("error[E0205]: cannot assign to a field of a temporary value"));
+    assert!(stderr.contains("error[E0205]: cannot call `push` on a temporary value"));
+}
+
+#[test]
+fn casts_between_structs() {
+    let source = r#"
+struct Point {
+    x: int,
+}
+
+fn main() {
+    const p = Point { x: 1 }
+    const q = p as Point
+    const s = "text" as string
+}
+"#;
+
+    let stderr = errors("casts_between_structs", source);
+    assert!(stderr.contains("error[E0314]: cannot cast `Point` as `Point`"));
+    assert!(stderr.contains("error[E0314]: cannot cast `string` as `string`"));
+}
+
+#[test]
+fn parser_recovers_at_new_lines() {
+    let source = r#"
+fn foo(a: int, b: int) {}
+
+fn main() {
+    let y: = 3
+    foo(1 2)
+    let z = (1 +
+        2 3)
+    const p = Point { x 5 }
+    foo(3, 4)
+}
+"#;
+
+    let stderr = errors("parser_recovers_at_new_lines", source);
+    assert!(stderr.contains("error[E0103]: expected an identifier as the type name, found `=`"));
+    assert!(
+        stderr.contains("error[E0103]: expected `)` to close the argument list, found `Int(2)`")
+    );
+    assert!(stderr.contains("parser_recovers_at_new_lines.ar:8:11"));
+    assert!(stderr.contains("error[E0101]: unexpected token `{`"));
+    assert!(stderr.contains("error: aborting due to 4 previous errors"));
+}
+
+#[test]
+fn main_that_fails_to_parse() {
+    let source = r#"
+fn main() {
+    let x = (1 + 2
+}
+"#;
+
+    let stderr = errors("main_that_fails_to_parse", source);
+    assert!(stderr.contains("error[E0103]: expected `)` to close the parenthesized expression"));
+    assert!(stderr.contains("main_that_fails_to_parse.ar:4:1\n"));
+    assert!(stderr.contains("4 | }\n  | ^ expected `)`\n"));
+    assert!(!stderr.contains("E0104"));
+    assert!(stderr.contains("error: aborting due to previous error\n"));
+
+    let stderr = errors("missing_main", "fn mian() {}\n");
+    assert!(stderr.contains("error[E0104]: no `main` function found"));
+    assert!(stderr.contains("missing_main.ar:1:1\n"));
+    assert!(stderr.contains("1 | fn mian() {}\n  | ^^^^^^^^^^^^\n"));
+}
+
+#[test]
+fn returns() {
+    let source = r#"
+fn sign(n: int) -> int {
+    if n < 0 { return -1 }
+    if n == 0 {
+        return 0
+    }
+    return 1
+}
+
+fn greet(loud: bool) {
+    if !loud {
+        println("hi")
+        return
+    }
+    println("HI")
+    return
+}
+
+fn stop() {
+    return
+    println("unreachable")
+}
+
+fn main() {
+    println(sign(-5))
+    println(sign(0))
+    println(sign(7))
+    greet(false)
+    greet(true)
+    stop()
+}
+"#;
+
+    if let Some(exe) = build("returns", source) {
+        assert_eq!(run(exe), "-1\n0\n1\nhi\nHI\n");
+    }
+
+    let source = r#"
+fn text() -> int {
+    return "a"
+}
+
+fn nothing() -> int {
+    return
+}
+
+fn main() {
+    text()
+    nothing()
+}
+"#;
+
+    let stderr = errors("return_errors", source);
+    assert!(stderr.contains("error[E0307]: mismatched types: expected `i64`, found `string`\n"));
+    assert!(stderr.contains("3 |     return \"a\"\n  |            ^^^ expected `i64`\n"));
+    assert!(stderr
+        .contains("error[E0309]: `return` without a value in a function that returns `i64`\n"));
+    assert!(stderr.contains("7 |     return\n  |     ^^^^^^\n"));
+}
+
+#[test]
+fn spans() {
+    let source = r#"
+fn main() {
+    let total = 1
+    println(totl + 1)
+    let ratio = total * 2.5
+}
+"#;
+
+    let stderr = errors("spans", source);
+    assert!(stderr.contains("error[E0201]: cannot find value `totl` in this scope\n"));
+    assert!(stderr.contains("spans.ar:4:13\n"));
+    assert!(stderr.contains("4 |     println(totl + 1)\n  |             ^^^^\n"));
+    assert!(stderr.contains("error[E0305]: cannot apply `*` to `i64` and `f64`\n"));
+    assert!(stderr.contains("spans.ar:5:17\n"));
+    assert!(stderr.contains("5 |     let ratio = total * 2.5\n  |                 ^^^^^^^^^^^\n"));
+}
+
+#[test]
+fn rendered_diagnostics() {
+    let source = r#"
+fn main() {
+    const c = 1
+    println(c)
+    println(c)
+    println(c)
+    println(c)
+    println(c)
+    println(c)
+    c = 2
+    let y: float = 3
+}
+"#;
+
+    let stderr = errors("rendered_diagnostics", source);
+    assert!(!stderr.contains('\x1b'));
+    assert!(stderr.starts_with("error[E0205]: cannot assign to constant `c`\n  --> "));
+    assert!(stderr.contains(
+        "rendered_diagnostics.ar:10:5
+   |
+10 |     c = 2
+   |     ^
+   |
+   = note: `c` is declared at line 3, column 5
+   = help: declare it with `let` to make it mutable
+
+error[E0307]: mismatched types: expected `f64`, found `i64`
+  --> "
+    ));
+    assert!(stderr.ends_with(
+        "rendered_diagnostics.ar:11:20
+   |
+11 |     let y: float = 3
+   |                    ^ expected `f64`
+   |
+   = help: convert the value with `as f64`
+
+error: aborting due to 2 previous errors
+"
+    ));
+}
+
+#[test]
+fn lexer_errors() {
+    let source = r#"
+fn main() {
+    let a = 1 @ 2
+    let b = 3 $ 4
+    let s = "abc
+}
+"#;
+
+    let stderr = errors("lexer_errors", source);
+    assert!(stderr.contains("error[E0003]: unexpected character `@`"));
+    assert!(stderr.contains("error[E0003]: unexpected character `$`"));
+    assert!(stderr.contains("error[E0001]: unterminated string literal"));
+    assert!(stderr.contains("lexer_errors.ar:5:13"));
+    assert!(stderr.contains("error: aborting due to 3 previous errors"));
+}
+
+#[test]
+fn expected_token_errors() {
+    let source = r#"
+struct Point {
+    x: int,
+}
+
+fn scale(factor 5) {}
+
+fn main() {
+    const p = Point { x: 1 }
+}
+"#;
+
+    let stderr = errors("expected_token_errors", source);
+    assert!(stderr.contains("error[E0103]: expected `:` after parameter name, found `Int(5)`"));
+    assert!(stderr.contains("expected_token_errors.ar:6:17"));
+    assert!(stderr.contains("error: aborting due to previous error"));
+}
+
+#[test]
+fn type_errors() {
+    let source = r#"
+fn add(a: int, b: int) -> int {
+    return a + b
+}
+
+fn main() {
+    const x: int = "text"
+    const y = add(1)
+    const z = add(1, true)
+}
+"#;
+
+    let stderr = errors("type_errors", source);
+    assert!(stderr.contains("error[E0307]: mismatched types: expected `i64`, found `string`"));
+    assert!(stderr.contains("error[E0306]: function `add` takes 2 arguments but 1 was supplied"));
+    assert!(stderr.contains("error[E0307]: mismatched types: expected `i64`, found `bool`"));
+    assert!(stderr.contains("error: aborting due to 3 previous errors"));
+}
+
+#[test]
+fn name_errors() {
+    let source = r#"
+fn main() {
+    const x = 1
+    const x = 2
+    println(missing)
+    x = 3
+    undefined(1)
+}
+"#;
+
+    let stderr = errors("name_errors", source);
+    assert!(stderr.contains("error[E0204]: `x` is already declared in this scope"));
+    assert!(stderr.contains("error[E0201]: cannot find value `missing` in this scope"));
+    assert!(stderr.contains("error[E0205]: cannot assign to constant `x`"));
+    assert!(stderr.contains("error[E0202]: cannot find function `undefined` in this scope"));
+    assert!(stderr.contains("error: aborting due to 4 previous errors"));
+}
+
+#[test]
+fn non_exhaustive_match() {
+    let source = r#"
+enum Shape {
+    Circle(f64),
+    Square(f64),
+    Point,
+}
+
+fn main() {
+    const shape = Shape::Point
+    match shape {
+        Shape::Circle(r) => println(r),
+    }
+}
+"#;
+
+    let stderr = errors("non_exhaustive_match", source);
+    assert!(stderr.contains(
+        "error[E0325]: non-exhaustive match: `Shape::Square` and `Shape::Point` not covered"
+    ));
+    assert!(stderr.contains("error: aborting due to previous error"));
+}
+
+#[test]
+fn generic_errors() {
+    let source = r#"
+fn unused<T>(value: T) -> int {
+    return "text"
+}
+
+fn add<T>(a: T, b: T) -> T {
+    return a + b
+}
+
+fn main() {
+    println(add(1, 2))
+    println(add(true, false))
+}
+"#;
+
+    let stderr = errors("generic_errors", source);
+    assert!(stderr.contains("error[E0307]: mismatched types: expected `i64`, found `string`"));
+    assert!(stderr.contains("error[E0329]: `add` cannot be called with `T = bool`"));
+    assert!(stderr
+        .contains("note: in the body of `add`, at 7:12: cannot apply `+` to `bool` and `bool`"));
+    assert!(stderr.contains("error: aborting due to 2 previous errors"));
+}
+
+#[test]
+fn float_printing() {
+    let source = r#"
+fn main() {
+    println(0.1 + 0.2)
+    println(1.0 / 3.0)
+    println(2.5)
+    const third: f32 = 1.0 / 3.0
+    println(third)
+    println(1e300 * 10.0)
+    println(100.0)
+    println(10f32)
+    println(1234567.0)
+    println(0.000001)
+}
+"#;
+
+    if let Some(exe) = build("float_printing", source) {
+        assert_eq!(
+            run(exe),
+            "0.30000000000000004\n0.3333333333333333\n2.5\n0.33333334\n1e+301\n100\n10\n1234567\n1e-06\n"
+        );
+    }
+}
+
+#[test]
+fn match_values_in_conditions() {
+    let source = r#"
+enum Step {
+    Go(int),
+    Stop,
+}
+
+fn next(n: int) -> Step {
+    if n < 3 {
+        return Step::Go(n + 1)
+    }
+    return Step::Stop
+}
+
+fn loud(value: bool) -> bool {
+    println("evaluated")
+    return value
+}
+
+fn main() {
+    let n = 0
+    while match next(n) {
+        Step::Go(_) => true,
+        Step::Stop => false,
+    } {
+        n += 1
+    }
+    println(n)
+
+    if n == 0 {
+        println("zero")
+    } else if match next(n) { Step::Go(_) => false, Step::Stop => true } {
+        println("stopped")
+    }
+
+    const skipped = false && match next(0) {
+        Step::Go(_) => loud(true),
+        Step::Stop => false,
+    }
+    const taken = true && match next(0) {
+        Step::Go(_) => loud(true),
+        Step::Stop => false,
+    }
+    println(skipped || taken)
+
+    const nested = match match next(1) { Step::Go(m) => next(m), Step::Stop => Step::Stop } {
+        Step::Go(m) => match next(m) {
+            Step::Go(k) => k * 10,
+            Step::Stop => 5,
+        },
+        Step::Stop => -1,
+    }
+    for i in 0..match next(1) { Step::Go(m) => m, Step::Stop => 0 } {
+        println(nested + i)
+    }
+}
+"#;
+
+    if let Some(exe) = build("match_values_in_conditions", source) {
+        assert_eq!(run(exe), "3\nstopped\nevaluated\ntrue\n5\n6\n");
+    }
+}
+
+#[test]
+fn shadowing() {
+    let source = r#"
+fn twice(x: int) -> int {
+    let x = x * 2
+    return x
+}
+
+fn main() {
+    const x = 1
+    println(twice(x))
+    if x == 1 {
+        const x = x + 10
+        println(x)
+        if x == 11 {
+            const x = "inner"
+            println(x)
+        }
+    }
+    println(x)
+}
+"#;
+
+    if let Some(exe) = build("shadowing", source) {
+        assert_eq!(run(exe), "2\n11\ninner\n1\n");
+    }
+}
+
+#[test]
+fn vec_loop_shadowing() {
+    let source = r#"
+fn main() {
+    const items: Vec<int> = [1, 2]
+    for x in items {
+        let x = x * 10
+        println(x)
+    }
+    for unused in items {
+        println("item")
+    }
+}
+"#;
+
+    if let Some(exe) = build("vec_loop_shadowing", source) {
+        assert_eq!(run(exe), "10\n20\nitem\nitem\n");
+    }
+}
+
+#[test]
+fn match_arm_shadowing() {
+    let source = r#"
+enum Shape {
+    Circle(int),
+    Point,
+}
+
+fn main() {
+    match Shape::Circle(2) {
+        Shape::Circle(r) => {
+            let r = r * 3
+            println(r)
+        }
+        Shape::Point => {}
+    }
+}
+"#;
+
+    if let Some(exe) = build("match_arm_shadowing", source) {
+        assert_eq!(run(exe), "6\n");
+    }
+}
+
+#[test]
+fn match_without_value() {
+    let source = r#"
+enum Shape {
+    Circle(int),
+    Point,
+}
+
+fn size(shape: Shape) -> int {
+    const size = match shape {
+        Shape::Circle(r) => {
+            return r
+        }
+        Shape::Point => {
+            return 0
+        }
+    }
+    return size
+}
+
+fn main() {
+    println(size(Shape::Point))
+}
+"#;
+
+    let stderr = errors("match_without_value", source);
+    assert!(stderr.contains("error[E0332]: cannot infer the type of this `match`"));
+    assert!(stderr.contains("match_without_value.ar:8:18"));
+    assert!(stderr.contains("error: aborting due to previous error"));
+}
+
+#[test]
+fn void_values() {
+    let source = r#"
+struct Holder {
+    value: void,
+}
+
+fn nothing() {}
+
+fn take(value: void) {}
+
+fn main() {
+    let items: Vec<void> = []
+    const calls = [nothing(), nothing()]
+}
+"#;
+
+    let stderr = errors("void_values", source);
+    assert!(stderr.contains("void_values.ar:3:12"));
+    assert!(stderr.contains("void_values.ar:8:16"));
+    assert!(stderr.contains("void_values.ar:11:20"));
+    assert_eq!(
+        stderr
+            .matches("error[E0333]: `void` is not the type of any value")
+            .count(),
+        3
+    );
+    assert!(stderr.contains("error[E0308]: this expression does not produce a value"));
+    assert!(stderr.contains("void_values.ar:12:20"));
+}
+
+#[test]
+fn vecs_share_their_items() {
+    let source = r#"
+struct Bag {
+    items: Vec<int>,
+}
+
+fn fill(items: Vec<int>) {
+    items.push(3)
+}
+
+fn main() {
+    const items: Vec<int> = []
+    let copy = items
+    copy.push(1)
+    copy = [10]
+    copy.push(11)
+    let bag = Bag { items: items }
+    bag.items[0] = 2
+    fill(items)
+    for item in items {
+        println(item)
+    }
+    println(copy.len())
+}
+"#;
+
+    if let Some(exe) = build("vecs_share_their_items", source) {
+        assert_eq!(run(exe), "2\n3\n2\n");
+    }
+}